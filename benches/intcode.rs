@@ -0,0 +1,69 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::fs;
+
+use advent_of_code::intcode::{asm::assemble, Program};
+use test::Bencher;
+
+/// Pushes and pops a 64 word stack above the code, the access pattern of recursive programs
+/// such as the day 9 BOOST program.
+const STACK_HEAVY: &str = "
+        RELADJ #4096
+loop:   ADD [i], #1 -> [rb+0]
+        ADD [rb+0], [rb+0] -> [rb+1]
+        MUL [rb+1], [rb+0] -> [rb+2]
+        RELADJ #1
+        ADD [d], #1 -> [d]
+        EQ [d], #64 -> [c]
+        JZ [c], #skip
+        RELADJ #-64
+        ADD #0, #0 -> [d]
+skip:   ADD [i], #1 -> [i]
+        LT [i], #100000 -> [c]
+        JNZ [c], #loop
+        HALT
+i:      .data 0
+c:      .data 0
+d:      .data 0
+";
+
+#[bench]
+fn bench_stack_heavy(b: &mut Bencher) {
+    let program = assemble::<i64>(STACK_HEAVY).unwrap();
+    b.iter(|| program.clone().execute().unwrap());
+}
+
+/// Runs a day's program with the given inputs, or does nothing if the input is not downloaded.
+fn bench_day(b: &mut Bencher, day: &str, input: &[i64]) {
+    let Ok(code) = fs::read_to_string(format!("data/inputs/{day}.txt")) else {
+        return;
+    };
+    let program = code.parse::<Program<i64>>().unwrap();
+    b.iter(|| {
+        let mut program = program.clone();
+        program.input(input.iter().copied());
+        program.by_ref().for_each(drop);
+    });
+}
+
+#[bench]
+fn bench_day_07(b: &mut Bencher) {
+    bench_day(b, "07", &[0, 0]);
+}
+
+#[bench]
+fn bench_day_09(b: &mut Bencher) {
+    bench_day(b, "09", &[2]);
+}
+
+#[bench]
+fn bench_day_17(b: &mut Bencher) {
+    bench_day(b, "17", &[0]);
+}
+
+#[bench]
+fn bench_day_19(b: &mut Bencher) {
+    bench_day(b, "19", &[20, 30]);
+}
@@ -6,7 +6,7 @@ pub fn part_one(input: &str) -> Option<u32> {
     let mut program: Program<u32> = input.parse().unwrap();
     program.set(1, 12);
     program.set(2, 2);
-    program.execute();
+    program.execute().ok()?;
     Some(program.get(0))
 }
 
@@ -16,8 +16,7 @@ pub fn part_two(input: &str) -> Option<u32> {
         let mut program = program.clone();
         program.set(1, n);
         program.set(2, v);
-        program.execute();
-        if program.get(0) == 19690720 {
+        if program.execute().is_ok() && program.get(0) == 19690720 {
             return Some(100 * n + v);
         }
     }
@@ -1,49 +1,23 @@
-use advent_of_code::intcode::Program;
+use advent_of_code::intcode::{pipeline::Pipeline, Program};
 use itertools::Itertools;
 
 advent_of_code::solution!(7);
 
-fn test_permutation(perm: &[i64], comp: &Program<i64>) -> i64 {
-    let mut input = 0;
-    for &num in perm.iter().take(5) {
-        let mut comp = comp.clone();
-        comp.input([num, input]);
-        input = comp.next().unwrap();
+/// Runs one amplifier per phase setting, either in a chain or in a feedback loop, and returns
+/// the last signal the final amplifier outputs.
+fn test_permutation(perm: &[i64], comp: &Program<i64>, feedback: bool) -> i64 {
+    let amps = perm.iter().map(|_| comp.clone());
+    let mut pipeline = if feedback {
+        Pipeline::ring(amps)
+    } else {
+        Pipeline::chain(amps)
+    };
+    for (amp, &phase) in perm.iter().enumerate() {
+        pipeline.input(amp, [phase]);
     }
-    input
-}
-
-fn test_permutation_looping(perm: &[i64], comp: &Program<i64>) -> i64 {
-    let mut comps = [
-        comp.clone(),
-        comp.clone(),
-        comp.clone(),
-        comp.clone(),
-        comp.clone(),
-    ];
-    comps[0].input([perm[0], 0]);
-    comps[1].input([perm[1]]);
-    comps[2].input([perm[2]]);
-    comps[3].input([perm[3]]);
-    comps[4].input([perm[4]]);
-    let mut last_seen = [0; 5];
-
-    for i in 0.. {
-        let i = i % 5;
-
-        match comps[i].next() {
-            Some(v) => {
-                comps[(i + 1) % 5].input([v]);
-                last_seen[i] = v;
-            }
-            None => {
-                if i == 4 {
-                    return last_seen[4];
-                }
-            }
-        }
-    }
-    0
+    pipeline.input(0, [0]);
+    pipeline.run().unwrap();
+    *pipeline.outputs(perm.len() - 1).last().unwrap()
 }
 
 pub fn part_one(input: &str) -> Option<i64> {
@@ -51,7 +25,7 @@ pub fn part_one(input: &str) -> Option<i64> {
     [0, 1, 2, 3, 4]
         .into_iter()
         .permutations(5)
-        .map(|perm| test_permutation(&perm, &comp))
+        .map(|perm| test_permutation(&perm, &comp, false))
         .max()
 }
 
@@ -60,7 +34,7 @@ pub fn part_two(input: &str) -> Option<i64> {
     [5, 6, 7, 8, 9]
         .into_iter()
         .permutations(5)
-        .map(|perm| test_permutation_looping(&perm, &comp))
+        .map(|perm| test_permutation(&perm, &comp, true))
         .max()
 }
 
@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use advent_of_code::util::ocr;
 use anyhow::anyhow;
 
 advent_of_code::solution!(8);
@@ -78,17 +79,6 @@ pub fn part_one(input: &str) -> Option<u32> {
 
 pub fn part_two(input: &str) -> Option<String> {
     let image = input.trim().parse::<Image>().unwrap();
-    let flattened = image.flatten();
-    for row in flattened {
-        for c in row {
-            let _c = match c {
-                0 => ' ',
-                1 => '█',
-                _ => unreachable!(),
-            };
-            // print!("{c}");
-        }
-        // println!();
-    }
-    Some("CYUAH".into())
+    let rows = image.flatten().map(|row| row.map(|p| p == 1));
+    ocr::read(&rows)
 }
@@ -2,7 +2,10 @@ use std::collections::{HashMap, HashSet};
 
 use advent_of_code::{
     intcode::Program,
-    util::point::{Dir, Turn, ORIGINI32},
+    util::{
+        ocr,
+        point::{Dir, Turn, ORIGINI32},
+    },
 };
 
 advent_of_code::solution!(11);
@@ -39,19 +42,12 @@ pub fn part_two(input: &str) -> Option<String> {
     let mut pos = ORIGINI32;
     let mut hull = HashMap::new();
     computer.input([1]);
-    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0, 0, 0, 0);
     while let Some((color, t)) = computer.next().zip(computer.next()) {
         hull.insert(pos, color);
         let turn = turn(t).unwrap();
         dir.turn(turn);
         pos += dir;
-        (min_x, min_y, max_x, max_y) = (
-            min_x.min(pos.x),
-            min_y.min(pos.y),
-            max_x.max(pos.x),
-            max_y.max(pos.y),
-        );
         computer.input(std::iter::once(*hull.get(&pos).unwrap_or(&0)));
     }
-    Some("KRZEAJHB".into())
+    ocr::read_points(&hull, |&color| color == 1)
 }
@@ -1,7 +1,16 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Read, Write},
+    process,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
 
-use advent_of_code::intcode::Program;
+use advent_of_code::intcode::{Event, Program};
 use anyhow::anyhow;
+use tinyjson::JsonValue;
 
 advent_of_code::solution!(13);
 
@@ -38,27 +47,436 @@ pub fn part_one(input: &str) -> Option<usize> {
     Some(tiles.values().filter(|v| **v == Tile::Block).count())
 }
 
-pub fn part_two(input: &str) -> Option<i32> {
-    let mut program = input.parse::<Program<i32>>().unwrap();
-    let mut tiles: HashMap<(i32, i32), Tile> = HashMap::new();
-    program.set(0, 2);
-    program.input([0]);
-    let mut score = 0;
-    while let (Some(x), Some(y), Some(tile)) = (program.next(), program.next(), program.next()) {
-        if (x, y) == (-1, 0) {
-            score = tile;
-        } else {
-            let tile = <Tile as TryFrom<_>>::try_from(tile).unwrap();
-            if tile == Tile::Ball {
-                if let Some(paddle_x) = tiles
-                    .iter()
-                    .find_map(|(&p, &t)| (t == Tile::Paddle).then_some(p.0))
-                {
-                    program.input([(x - paddle_x).signum()]);
+/// Time between frames when watching the autopilot, and the length of a turn when playing.
+const AUTOPILOT_FRAME: Duration = Duration::from_millis(10);
+const PLAYER_FRAME: Duration = Duration::from_millis(80);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Arcade {
+    program: Program<i32>,
+    tiles: HashMap<(i32, i32), Tile>,
+    score: i32,
+    ball_x: i32,
+    paddle_x: i32,
+}
+
+impl From<Program<i32>> for Arcade {
+    fn from(program: Program<i32>) -> Self {
+        Self {
+            program,
+            tiles: HashMap::new(),
+            score: 0,
+            ball_x: 0,
+            paddle_x: 0,
+        }
+    }
+}
+
+impl Arcade {
+    /// Inserts quarters and starts the game.
+    fn new(input: &str) -> Self {
+        let mut program = input.parse::<Program<i32>>().unwrap();
+        program.set(0, 2);
+        Self::from(program)
+    }
+
+    /// Runs until the game wants the joystick position. Returns `false` once the game is over.
+    fn tick(&mut self) -> anyhow::Result<bool> {
+        let mut out = Vec::new();
+        let event = self.program.run_until_input(&mut out)?;
+        for draw in out.chunks_exact(3) {
+            let &[x, y, tile] = draw else { unreachable!() };
+            if (x, y) == (-1, 0) {
+                self.score = tile;
+                continue;
+            }
+            let tile = Tile::try_from(tile)?;
+            match tile {
+                Tile::Ball => self.ball_x = x,
+                Tile::Paddle => self.paddle_x = x,
+                _ => {}
+            }
+            self.tiles.insert((x, y), tile);
+        }
+        Ok(event == Event::NeedsInput)
+    }
+
+    /// Keeps the paddle under the ball.
+    fn autopilot(&self) -> i32 {
+        (self.ball_x - self.paddle_x).signum()
+    }
+
+    /// The width and height of the screen in tiles.
+    fn size(&self) -> (i32, i32) {
+        let width = self.tiles.keys().map(|p| p.0 + 1).max().unwrap_or(0);
+        let height = self.tiles.keys().map(|p| p.1 + 1).max().unwrap_or(0);
+        (width, height)
+    }
+
+    /// Draws the screen with ANSI escapes, starting from the top left corner of the terminal.
+    fn render(&self) -> String {
+        let (width, height) = self.size();
+        let mut frame = "\x1b[H".to_string();
+        for y in 0..height {
+            for x in 0..width {
+                frame.push_str(match self.tiles.get(&(x, y)) {
+                    None | Some(Tile::Empty) => " ",
+                    Some(Tile::Wall) => "\x1b[90m█\x1b[0m",
+                    Some(Tile::Block) => "\x1b[36m▒\x1b[0m",
+                    Some(Tile::Paddle) => "\x1b[1;33m▀\x1b[0m",
+                    Some(Tile::Ball) => "\x1b[1;31m●\x1b[0m",
+                });
+            }
+            frame.push_str("\r\n");
+        }
+        frame.push_str(&format!("Score: {}\x1b[K\r\n", self.score));
+        frame
+    }
+}
+
+/// An asciinema (v2) recording of the screen.
+#[derive(Clone, PartialEq, Debug)]
+struct Recording {
+    width: usize,
+    height: usize,
+    events: Vec<(f64, String)>,
+}
+
+impl Recording {
+    fn save(&self, path: &str) -> io::Result<()> {
+        let header = HashMap::from([
+            ("version".to_string(), JsonValue::Number(2.0)),
+            ("width".to_string(), JsonValue::Number(self.width as f64)),
+            ("height".to_string(), JsonValue::Number(self.height as f64)),
+        ]);
+        let mut cast = JsonValue::Object(header).stringify().unwrap();
+        for (time, data) in &self.events {
+            let event = JsonValue::Array(vec![
+                JsonValue::Number(*time),
+                JsonValue::String("o".to_string()),
+                JsonValue::String(data.clone()),
+            ]);
+            cast.push('\n');
+            cast.push_str(&event.stringify().unwrap());
+        }
+        cast.push('\n');
+        fs::write(path, cast)
+    }
+
+    /// Reads a recording, keeping only what was printed.
+    fn load(path: &str) -> anyhow::Result<Self> {
+        let cast = fs::read_to_string(path)?;
+        let mut lines = cast.lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow!("empty recording"))?
+            .parse::<JsonValue>()?;
+        let size = |name: &str| match header.get::<HashMap<_, _>>().and_then(|h| h.get(name)) {
+            Some(JsonValue::Number(n)) => Ok(*n as usize),
+            _ => Err(anyhow!("the header has no {name}")),
+        };
+        let (width, height) = (size("width")?, size("height")?);
+        let mut events = Vec::new();
+        for line in lines {
+            let event = line.parse::<JsonValue>()?;
+            match event.get::<Vec<_>>().map(Vec::as_slice) {
+                Some(
+                    [JsonValue::Number(time), JsonValue::String(kind), JsonValue::String(data)],
+                ) => {
+                    if kind == "o" {
+                        events.push((*time, data.clone()));
+                    }
+                }
+                _ => return Err(anyhow!("{line} is not an event")),
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            events,
+        })
+    }
+
+    /// Plays the recording to `out` at its original speed. Returns the score on the last frame.
+    fn replay(&self, out: &mut impl Write) -> io::Result<Option<i32>> {
+        let start = Instant::now();
+        for (time, data) in &self.events {
+            thread::sleep(Duration::from_secs_f64(*time).saturating_sub(start.elapsed()));
+            write!(out, "{data}")?;
+            out.flush()?;
+        }
+        let score = self.events.last().and_then(|(_, data)| {
+            let score = &data[data.rfind("Score: ")? + "Score: ".len()..];
+            score
+                .split(|c: char| !c.is_ascii_digit())
+                .next()?
+                .parse()
+                .ok()
+        });
+        Ok(score)
+    }
+}
+
+/// Changes terminal settings. Does nothing if stdin is not a terminal.
+fn stty(args: &[&str]) {
+    let _ = process::Command::new("stty")
+        .args(args)
+        .stdin(process::Stdio::inherit())
+        .stderr(process::Stdio::null())
+        .status();
+}
+
+/// Takes over the terminal while the game is on screen, and gives it back when dropped, even if
+/// the game panics.
+struct Screen {
+    /// Whether keys are delivered as they are pressed rather than line by line. Ctrl-C is then
+    /// read as a key instead of killing the game, so that the terminal is always given back.
+    raw: bool,
+}
+
+impl Screen {
+    fn new(raw: bool) -> Self {
+        if raw {
+            stty(&["-icanon", "-echo", "-isig"]);
+        }
+        print!("\x1b[2J\x1b[?25l");
+        Self { raw }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        print!("\x1b[?25h");
+        let _ = io::stdout().flush();
+        if self.raw {
+            stty(&["icanon", "echo", "isig"]);
+        }
+    }
+}
+
+/// Reads the next joystick move from `bytes`: `a`, `h` or the left arrow to go left, `d`, `l` or
+/// the right arrow to go right. `q` or Ctrl-C give up, which returns `Some(None)`. Returns `None`
+/// once the bytes run out.
+fn next_key(bytes: &mut impl Iterator<Item = u8>) -> Option<Option<i32>> {
+    while let Some(b) = bytes.next() {
+        return Some(match b {
+            b'a' | b'h' => Some(-1),
+            b'd' | b'l' => Some(1),
+            b'q' | 0x03 => None,
+            // Arrow keys are sent as ESC [ A to ESC [ D.
+            0x1b if bytes.next() == Some(b'[') => match bytes.next() {
+                Some(b'D') => Some(-1),
+                Some(b'C') => Some(1),
+                _ => continue,
+            },
+            _ => continue,
+        });
+    }
+    None
+}
+
+/// Reads joystick moves from the keyboard, see [`next_key`]. The terminal should be in raw
+/// mode, see [`Screen`].
+fn spawn_keyboard() -> Receiver<Option<i32>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = io::stdin().lock().bytes().map_while(Result::ok);
+        while let Some(key) = next_key(&mut bytes) {
+            if tx.send(key).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Shown under the screen when playing, and not recorded.
+const CONTROLS: &str = "a/d or arrow keys to move, q or Ctrl-C to give up\x1b[K\r\n";
+
+/// Plays the game on screen, steered by the autopilot or from the keyboard, and optionally
+/// records it. Returns the final score, or `None` if the player gave up.
+fn play_visual(input: &str, interactive: bool, record: Option<&str>) -> Option<i32> {
+    let mut arcade = Arcade::new(input);
+    let screen = Screen::new(interactive);
+    let keys = interactive.then(spawn_keyboard);
+    let frame_time = if interactive {
+        PLAYER_FRAME
+    } else {
+        AUTOPILOT_FRAME
+    };
+    let mut recording = Vec::new();
+
+    let start = Instant::now();
+    let mut frame = 0;
+    let finished = loop {
+        let playing = arcade.tick().ok()?;
+        let screen = arcade.render();
+        print!("{screen}{}", if interactive { CONTROLS } else { "" });
+        let _ = io::stdout().flush();
+        let time = frame_time * frame;
+        let clear = if frame == 0 { "\x1b[2J" } else { "" };
+        recording.push((time.as_secs_f64(), format!("{clear}{screen}")));
+        frame += 1;
+        if !playing {
+            break true;
+        }
+
+        thread::sleep((time + frame_time).saturating_sub(start.elapsed()));
+        let joystick = match &keys {
+            Some(keys) => {
+                // Giving up wins over any moves made in the same turn.
+                let pressed = keys.try_iter().collect::<Vec<_>>();
+                if pressed.contains(&None) {
+                    break false;
                 }
+                pressed.last().copied().flatten().unwrap_or(0)
             }
-            tiles.insert((x, y), tile);
+            None => arcade.autopilot(),
+        };
+        arcade.program.input([joystick]);
+    };
+
+    drop(screen);
+    if let Some(path) = record {
+        let (width, height) = arcade.size();
+        let recording = Recording {
+            // One more line for the score.
+            width: width as usize,
+            height: height as usize + 1,
+            events: recording,
+        };
+        match recording.save(path) {
+            Ok(()) => eprintln!("Saved recording to {path}"),
+            Err(e) => eprintln!("Could not save recording to {path}: {e}"),
+        }
+    }
+    finished.then_some(arcade.score)
+}
+
+pub fn part_two(input: &str) -> Option<i32> {
+    let args = env::args().collect::<Vec<_>>();
+    let flag = |name: &str| args.iter().any(|a| a == name);
+    let value = |name: &str| args.iter().skip_while(|&a| a != name).nth(1);
+    if let Some(path) = value("--playback") {
+        let score = Recording::load(path).and_then(|r| Ok(r.replay(&mut io::stdout())?));
+        return score.unwrap_or_else(|e| {
+            eprintln!("Could not play back {path}: {e:#}");
+            None
+        });
+    }
+    let interactive = flag("--interactive");
+    let record = value("--record");
+    if interactive || flag("--visual") || record.is_some() {
+        return play_visual(input, interactive, record.map(String::as_str));
+    }
+
+    let mut arcade = Arcade::new(input);
+    while arcade.tick().ok()? {
+        arcade.program.input([arcade.autopilot()]);
+    }
+    Some(arcade.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use advent_of_code::intcode::asm::assemble;
+
+    use super::*;
+
+    /// Draws a wall, a block, the paddle and the ball, reads the joystick, then scores 7 for
+    /// breaking the block and ends.
+    const GAME: &str = "
+            OUTPUT #0
+            OUTPUT #0
+            OUTPUT #1
+            OUTPUT #1
+            OUTPUT #0
+            OUTPUT #2
+            OUTPUT #0
+            OUTPUT #1
+            OUTPUT #3
+            OUTPUT #1
+            OUTPUT #1
+            OUTPUT #4
+            INPUT -> [joystick]
+            OUTPUT #-1
+            OUTPUT #0
+            OUTPUT #7
+            OUTPUT #1
+            OUTPUT #0
+            OUTPUT #0
+            HALT
+    joystick: .data 0
+    ";
+
+    fn arcade() -> Arcade {
+        Arcade::from(assemble::<i32>(GAME).unwrap())
+    }
+
+    #[test]
+    fn test_tick() {
+        let mut arcade = arcade();
+        assert!(arcade.tick().unwrap());
+        assert_eq!((arcade.ball_x, arcade.paddle_x), (1, 0));
+        assert_eq!(arcade.autopilot(), 1);
+        arcade.program.input([arcade.autopilot()]);
+        assert!(!arcade.tick().unwrap());
+        assert_eq!(arcade.score, 7);
+        assert_eq!(arcade.tiles[&(1, 0)], Tile::Empty);
+
+        let mut broken = Arcade::from("104,0,104,0,104,9,99".parse::<Program<i32>>().unwrap());
+        assert_eq!(
+            broken.tick().unwrap_err().to_string(),
+            "9 is not a valid tile id"
+        );
+    }
+
+    #[test]
+    fn test_next_key() {
+        let mut bytes = b"ax\x1b[Cd\x1b[Aq\x03".iter().copied();
+        let keys = std::iter::from_fn(|| next_key(&mut bytes)).collect::<Vec<_>>();
+        assert_eq!(keys, [Some(-1), Some(1), Some(1), None, None]);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut arcade = arcade();
+        arcade.tick().unwrap();
+        assert_eq!(arcade.size(), (2, 2));
+        assert_eq!(
+            arcade.render(),
+            "\x1b[H\x1b[90m█\x1b[0m\x1b[36m▒\x1b[0m\r\n\
+             \x1b[1;33m▀\x1b[0m\x1b[1;31m●\x1b[0m\r\n\
+             Score: 0\x1b[K\r\n"
+        );
+    }
+
+    #[test]
+    fn test_recording() {
+        let path = env::temp_dir().join(format!("aoc-13-{}.cast", process::id()));
+        let path = path.to_str().unwrap();
+        let mut arcade = arcade();
+        let mut events = Vec::new();
+        while {
+            let playing = arcade.tick().unwrap();
+            events.push((events.len() as f64 * 0.001, arcade.render()));
+            playing
+        } {
+            arcade.program.input([0]);
         }
+        let recording = Recording {
+            width: 2,
+            height: 3,
+            events,
+        };
+        recording.save(path).unwrap();
+        let loaded = Recording::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded, recording);
+
+        let mut out = Vec::new();
+        assert_eq!(loaded.replay(&mut out).unwrap(), Some(7));
+        let frames = recording.events.iter().map(|(_, data)| data.as_str());
+        assert_eq!(String::from_utf8(out).unwrap(), frames.collect::<String>());
     }
-    Some(score)
 }
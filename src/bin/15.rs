@@ -1,27 +1,111 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use advent_of_code::{intcode::Program, util::graph::Graph};
-use rand::{rngs::ThreadRng, Rng};
 
 advent_of_code::solution!(15);
 
+/// Movement commands in the order the explorer tries them: north, south, west, east.
+const MOVES: [i32; 4] = [1, 2, 3, 4];
+
+fn step(pos: (i32, i32), dir: i32) -> (i32, i32) {
+    match dir {
+        1 => (pos.0, pos.1 - 1),
+        2 => (pos.0, pos.1 + 1),
+        3 => (pos.0 - 1, pos.1),
+        4 => (pos.0 + 1, pos.1),
+        _ => unreachable!(),
+    }
+}
+
+fn reverse(dir: i32) -> i32 {
+    match dir {
+        1 => 2,
+        2 => 1,
+        3 => 4,
+        _ => 3,
+    }
+}
+
+/// The area around the droid, which starts at `(0, 0)`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
 struct Map {
-    tiles: HashMap<(i32, i32), bool>,
+    walls: HashSet<(i32, i32)>,
+    open: HashSet<(i32, i32)>,
+    oxygen: Option<(i32, i32)>,
+}
+
+impl Map {
+    /// Maps everything the droid can reach by walking it depth first and backing up at dead
+    /// ends, until it is back at the start.
+    fn explore(mut droid: Program<i32>) -> Self {
+        let mut map = Map::default();
+        map.open.insert((0, 0));
+        // The path from the start, as positions and the move that led to each of them.
+        let mut path = vec![((0, 0), None)];
+        while let Some(&(pos, came_by)) = path.last() {
+            let unknown = MOVES.into_iter().find(|&dir| {
+                let next = step(pos, dir);
+                !map.walls.contains(&next) && !map.open.contains(&next)
+            });
+            match unknown {
+                Some(dir) => {
+                    droid.input([dir]);
+                    let next = step(pos, dir);
+                    match droid.next().unwrap() {
+                        0 => {
+                            map.walls.insert(next);
+                        }
+                        status => {
+                            if status == 2 {
+                                map.oxygen = Some(next);
+                            }
+                            map.open.insert(next);
+                            path.push((next, Some(dir)));
+                        }
+                    }
+                }
+                None => {
+                    path.pop();
+                    if let Some(dir) = came_by {
+                        droid.input([reverse(dir)]);
+                        droid.next().unwrap();
+                    }
+                }
+            }
+        }
+        map
+    }
+
+    /// The number of steps from `source` to every open tile reachable from it.
+    fn distances(&self, source: (i32, i32)) -> HashMap<(i32, i32), usize> {
+        let mut distances = HashMap::from([(source, 0)]);
+        let mut queue = VecDeque::from([source]);
+        while let Some(pos) = queue.pop_front() {
+            let d = distances[&pos];
+            for (next, _) in self.neighbors(pos) {
+                distances.entry(next).or_insert_with(|| {
+                    queue.push_back(next);
+                    d + 1
+                });
+            }
+        }
+        distances
+    }
+
+    /// How long it takes oxygen released at `source` to fill the area.
+    fn fill_time(&self, source: (i32, i32)) -> Option<usize> {
+        self.distances(source).into_values().max()
+    }
 }
 
 impl Graph for Map {
     type Node = (i32, i32);
 
     fn neighbors(&self, node: Self::Node) -> impl Iterator<Item = (Self::Node, usize)> {
-        (node.0 - 1..=node.0 + 1)
-            .flat_map(move |x| (node.1 - 1..=node.1 + 1).map(move |y| (x, y)))
-            .filter(move |p| (p.0 != node.0 && p.1 == node.1) || (p.0 == node.0 && p.1 != node.1))
-            .filter(|p| {
-                self.tiles
-                    .get(p)
-                    .copied()
-                    .is_some_and(std::convert::identity)
-            })
+        MOVES
+            .into_iter()
+            .map(move |dir| step(node, dir))
+            .filter(|p| self.open.contains(p))
             .map(|p| (p, 1))
     }
 
@@ -31,101 +115,40 @@ impl Graph for Map {
 }
 
 pub fn part_one(input: &str) -> Option<usize> {
-    let mut computer: Program<i32> = input.parse().unwrap();
-    let (mut x, mut y) = (0, 0);
-    let mut tiles = HashMap::new();
-    let mut rng = ThreadRng::default();
-    tiles.insert((x, y), true);
-    let mut already_tried = Vec::with_capacity(4);
-    let mut oxygen = (0, 0);
-    for _ in 0..1000000 {
-        let dir = {
-            loop {
-                let candidate = rng.gen_range(1..=4);
-                if !already_tried.contains(&candidate) {
-                    break candidate;
-                }
-            }
-        };
-        computer.input(std::iter::once(dir));
-        let status = computer.next().unwrap();
-        let new_pos = match dir {
-            1 => (x, y - 1),
-            2 => (x, y + 1),
-            3 => (x - 1, y),
-            4 => (x + 1, y),
-            _ => unreachable!(),
-        };
-        match status {
-            0 => {
-                tiles.insert(new_pos, false);
-                already_tried.push(dir);
-            }
-            1 => {
-                (x, y) = new_pos;
-                tiles.insert(new_pos, true);
-                already_tried.clear();
-            }
-            2 => {
-                (x, y) = new_pos;
-                tiles.insert(new_pos, true);
-                oxygen = new_pos;
-            }
-            _ => unreachable!(),
-        }
-    }
-    let map = Map { tiles };
-    map.a_star_distance((0, 0), oxygen)
+    let map = Map::explore(input.parse().unwrap());
+    map.a_star_distance((0, 0), map.oxygen?)
 }
 
 pub fn part_two(input: &str) -> Option<usize> {
-    let mut computer: Program<i32> = input.parse().unwrap();
-    let (mut x, mut y) = (0, 0);
-    let mut tiles = HashMap::new();
-    let mut rng = ThreadRng::default();
-    tiles.insert((x, y), true);
-    let mut already_tried = Vec::with_capacity(4);
-    let mut oxygen = (0, 0);
-    for _ in 0..1000000 {
-        let dir = {
-            loop {
-                let candidate = rng.gen_range(1..=4);
-                if !already_tried.contains(&candidate) {
-                    break candidate;
-                }
-            }
-        };
-        computer.input(std::iter::once(dir));
-        let status = computer.next().unwrap();
-        let new_pos = match dir {
-            1 => (x, y - 1),
-            2 => (x, y + 1),
-            3 => (x - 1, y),
-            4 => (x + 1, y),
-            _ => unreachable!(),
-        };
-        match status {
-            0 => {
-                tiles.insert(new_pos, false);
-                already_tried.push(dir);
-            }
-            1 => {
-                (x, y) = new_pos;
-                tiles.insert(new_pos, true);
-                already_tried.clear();
-            }
-            2 => {
-                (x, y) = new_pos;
-                tiles.insert(new_pos, true);
-                oxygen = new_pos;
-            }
-            _ => unreachable!(),
-        }
+    let map = Map::explore(input.parse().unwrap());
+    map.fill_time(map.oxygen?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_one() {
+        let result = part_one(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(8));
+    }
+
+    #[test]
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(12));
+    }
+
+    #[test]
+    fn test_explore() {
+        let droid = advent_of_code::template::read_file("examples", DAY)
+            .parse::<Program<i32>>()
+            .unwrap();
+        let map = Map::explore(droid.clone());
+        assert_eq!(map.open.len(), 24);
+        assert_eq!(map.oxygen, Some((6, -2)));
+        assert_eq!(map.distances((0, 0)).len(), map.open.len());
+        assert_eq!(Map::explore(droid), map);
     }
-    let map = Map { tiles };
-    map.tiles
-        .iter()
-        .filter(|(_, e)| **e)
-        .map(|(v, _)| map.a_star_distance(oxygen, *v).unwrap())
-        .max()
 }
@@ -4,7 +4,7 @@ use std::{
 };
 
 use advent_of_code::{
-    intcode::Program,
+    intcode::{ascii::AsciiComputer, Program},
     util::point::{Dir, Pt, Turn},
 };
 use strum::IntoEnumIterator;
@@ -12,12 +12,8 @@ use strum::IntoEnumIterator;
 advent_of_code::solution!(17);
 
 pub fn part_one(input: &str) -> Option<usize> {
-    let computer = input.parse::<Program<i32>>().unwrap();
-    let mut map = String::new();
-    for c in computer {
-        let c = char::from_u32(c as u32).unwrap();
-        map.push(c);
-    }
+    let mut computer = AsciiComputer::from(input.parse::<Program<i32>>().unwrap());
+    let map = computer.read_until_prompt().ok()?;
     let map = map
         .lines()
         .map(|l| l.chars().collect::<Vec<_>>())
@@ -125,16 +121,18 @@ impl _Map {
 }
 
 pub fn part_two(input: &str) -> Option<i32> {
-    let mut computer = input.parse::<Program<i32>>().unwrap();
-    computer.set(0, 2);
-    computer.input(b"A,A,B,C,A,C,A,B,C,B\n".iter().map(|c| *c as i32));
-    computer.input(b"R,12,L,8,R,6\n".iter().map(|c| *c as i32));
-    computer.input(b"R,12,L,6,R,6,R,8,R,6\n".iter().map(|c| *c as i32));
-    computer.input(b"L,8,R,8,R,6,R,12\n".iter().map(|c| *c as i32));
-    computer.input(b"n\n".iter().map(|c| *c as i32));
-    let mut last = 0;
-    for c in computer {
-        last = c;
+    let mut program = input.parse::<Program<i32>>().unwrap();
+    program.set(0, 2);
+    let mut computer = AsciiComputer::from(program);
+    for line in [
+        "A,A,B,C,A,C,A,B,C,B",
+        "R,12,L,8,R,6",
+        "R,12,L,6,R,6,R,8,R,6",
+        "L,8,R,8,R,6,R,12",
+        "n",
+    ] {
+        computer.send_line(line);
     }
-    Some(last)
+    computer.read_until_prompt().ok()?;
+    computer.value().copied()
 }
@@ -1,22 +1,15 @@
-use advent_of_code::intcode::Program;
+use advent_of_code::intcode::{threaded::par_eval, Program};
+use itertools::iproduct;
 
 advent_of_code::solution!(19);
 
 pub fn part_one(input: &str) -> Option<u32> {
-    let mut comp = input.parse::<Program<i32>>().unwrap();
-    let mut num_pulled = 0;
-    comp.cache();
-    for y in 0..50 {
-        for x in 0..50 {
-            comp.reset();
-            comp.input([x, y]);
-            let res = comp.next().unwrap();
-            if res == 1 {
-                num_pulled += 1;
-            }
-        }
-    }
-    Some(num_pulled)
+    let comp = input.parse::<Program<i32>>().unwrap();
+    let pulled = par_eval(&comp, iproduct!(0..50, 0..50), |mut comp, (x, y)| {
+        comp.input([x, y]);
+        comp.next() == Some(1)
+    });
+    Some(pulled.into_iter().filter(|&p| p).count() as u32)
 }
 
 fn square_fits(program: &mut Program<i64>, x: i64, y: i64) -> bool {
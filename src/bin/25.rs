@@ -1,63 +1,510 @@
-// use std::io::{self, BufRead};
+use std::{
+    collections::HashSet,
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+};
 
-use advent_of_code::intcode::{NonBlockProgram, Program};
+use advent_of_code::intcode::{ascii::AsciiComputer, save, Program};
 
 advent_of_code::solution!(25);
 
-fn command(i: &str) -> impl Iterator<Item = i64> + use<'_> {
-    i.as_bytes().iter().map(|i| *i as i64)
+/// Where `--interactive` saves the commands that were typed.
+const TRANSCRIPT: &str = "data/transcripts/25.txt";
+
+/// Handles `save <file>` and `load <file>`, which save the whole machine or resume a saved one
+/// instead of going to the game. Returns `None` for any other command, and otherwise whether a
+/// game was loaded.
+fn save_or_load(computer: &mut AsciiComputer<i64>, cmd: &str) -> Option<bool> {
+    let result = match cmd.split_once(' ') {
+        Some(("save", path)) => fs::write(path, save::save(computer.program()))
+            .map(|_| (format!("Saved the game to {path}"), false))
+            .map_err(anyhow::Error::from),
+        Some(("load", path)) => fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| save::load(&text))
+            .map(|program| {
+                *computer = AsciiComputer::from(program);
+                (format!("Loaded the game from {path}"), true)
+            }),
+        _ => return None,
+    };
+    match result {
+        Ok((message, loaded)) => {
+            println!("{message}\n");
+            Some(loaded)
+        }
+        Err(e) => {
+            println!("Could not {cmd}: {e:#}\n");
+            Some(false)
+        }
+    }
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut computer: NonBlockProgram<_> = input.parse::<Program<i64>>().unwrap().into();
-    let mut commands = [
-        "south\n",
-        "south\n",
-        "south\n",
-        "take astrolabe\n",
-        "south\n",
-        "take mug\n",
-        "north\n",
-        "north\n",
-        "west\n",
-        "north\n",
-        "north\n",
-        "take wreath\n",
-        "south\n",
-        "south\n",
-        "east\n",
-        "north\n",
-        "west\n",
-        "take sand\n",
-        "west\n",
-        "west\n",
-        "west\n",
-    ]
-    .into_iter();
-    // let stdin = io::stdin();
-    while let Some(c) = computer.next() {
-        match c {
-            Ok(c) => {
-                if let Some(c) = char::from_u32(c as u32) {
-                    print!("{c}");
-                }
+/// Prints the game's text and answers every prompt with the next line of `commands`, until the
+/// game ends or the commands run out. Returns the commands that replay the game and the last
+/// text the game printed: saves are left out, and loading a game starts them over from the
+/// `load`.
+fn play(
+    computer: &mut AsciiComputer<i64>,
+    commands: impl BufRead,
+    echo: bool,
+) -> Option<(Vec<String>, String)> {
+    let mut sent = Vec::new();
+    let mut commands = commands.lines();
+    loop {
+        let text = computer.read_until_prompt().ok()?;
+        print!("{text}");
+        let _ = io::stdout().flush();
+        let Some(Ok(cmd)) = commands.next().filter(|_| !computer.halted()) else {
+            return Some((sent, text));
+        };
+        if echo {
+            println!("{cmd}");
+        }
+        match save_or_load(computer, &cmd) {
+            None => {
+                computer.send_line(&cmd);
+                sent.push(cmd);
+            }
+            Some(true) => sent = vec![cmd],
+            Some(false) => {}
+        }
+    }
+}
+
+/// How many instructions a command may take before the game is assumed to be stuck.
+const MAX_STEPS: usize = 1_000_000;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Room {
+    name: String,
+    doors: Vec<String>,
+    items: Vec<String>,
+}
+
+/// Parses the last room description in `text`. Being thrown out of a room prints two rooms,
+/// and the one you end up in comes last.
+fn parse_room(text: &str) -> Option<Room> {
+    let start = text.rfind("== ")?;
+    let mut lines = text[start..].lines();
+    let name = lines
+        .next()?
+        .trim_matches(|c| c == '=' || c == ' ')
+        .to_string();
+    let mut room = Room {
+        name,
+        doors: Vec::new(),
+        items: Vec::new(),
+    };
+    let mut list = None;
+    for line in lines {
+        match line {
+            "Doors here lead:" => list = Some(&mut room.doors),
+            "Items here:" => list = Some(&mut room.items),
+            _ => match (line.strip_prefix("- "), list.as_mut()) {
+                (Some(entry), Some(list)) => list.push(entry.to_string()),
+                _ => list = None,
+            },
+        }
+    }
+    Some(room)
+}
+
+fn opposite(door: &str) -> Option<&'static str> {
+    match door {
+        "north" => Some("south"),
+        "south" => Some("north"),
+        "east" => Some("west"),
+        "west" => Some("east"),
+        _ => None,
+    }
+}
+
+/// What the solver needs from the game, so that the tests can script one.
+trait Game: Clone {
+    /// Reads the game's text up to its next prompt, or `None` if it got stuck.
+    fn read(&mut self) -> Option<String>;
+
+    /// Sends `cmd` and reads the answer.
+    fn send(&mut self, cmd: &str) -> Option<String>;
+
+    fn halted(&self) -> bool;
+}
+
+impl Game for AsciiComputer<i64> {
+    fn read(&mut self) -> Option<String> {
+        self.read_until_prompt_within(MAX_STEPS).ok()?
+    }
+
+    fn send(&mut self, cmd: &str) -> Option<String> {
+        self.send_line(cmd);
+        self.read()
+    }
+
+    fn halted(&self) -> bool {
+        AsciiComputer::halted(self)
+    }
+}
+
+/// Sends a command and returns the game's answer, or `None` if the game ended or got stuck.
+fn command(game: &mut impl Game, cmd: &str) -> Option<String> {
+    let text = game.send(cmd)?;
+    (!game.halted()).then_some(text)
+}
+
+#[derive(Default)]
+struct Explorer {
+    visited: HashSet<String>,
+    items: Vec<String>,
+    /// The way from the start to the Security Checkpoint, and the door to the floor behind it.
+    checkpoint: Option<(Vec<String>, String)>,
+}
+
+impl Explorer {
+    /// Picks up every safe item reachable from `room` and returns to it.
+    fn explore(
+        &mut self,
+        computer: &mut impl Game,
+        room: &Room,
+        path: &mut Vec<String>,
+    ) -> Option<()> {
+        self.visited.insert(room.name.clone());
+        for item in &room.items {
+            if is_safe(computer, room, item) {
+                command(computer, &format!("take {item}"))?;
+                self.items.push(item.clone());
+            }
+        }
+        for door in &room.doors {
+            let next = parse_room(&command(computer, door)?)?;
+            if next.name == room.name {
+                // Thrown back by the pressure-sensitive floor.
+                self.checkpoint = Some((path.clone(), door.clone()));
+                continue;
             }
-            Err(false) => {}
-            Err(true) => {
-                let cmd = commands.next().unwrap();
-                print!("{cmd}");
-                computer.program.input(command(cmd));
-                // let mut buffer = String::new();
-                // let mut handle = stdin.lock();
-                // handle.read_line(&mut buffer).unwrap();
-                // computer.program.input(command(&buffer));
+            if !self.visited.contains(&next.name) {
+                path.push(door.clone());
+                self.explore(computer, &next, path)?;
+                path.pop();
             }
+            command(computer, opposite(door)?)?;
+        }
+        Some(())
+    }
+}
+
+/// Tries taking `item` on a copy of the game. Some items end the game, never give control back,
+/// or stop the droid from moving.
+fn is_safe(computer: &impl Game, room: &Room, item: &str) -> bool {
+    let mut trial = computer.clone();
+    command(&mut trial, &format!("take {item}")).is_some()
+        && room.doors.first().is_some_and(|door| {
+            command(&mut trial, door).is_some_and(|text| parse_room(&text).is_some())
+        })
+}
+
+/// Collects every safe item, walks to the Security Checkpoint and tries every combination of
+/// items on the pressure-sensitive floor.
+fn solve(mut computer: impl Game) -> Option<u32> {
+    let start = parse_room(&computer.read()?)?;
+    let mut explorer = Explorer::default();
+    explorer.explore(&mut computer, &start, &mut Vec::new())?;
+    let (path, floor) = explorer.checkpoint?;
+    for door in &path {
+        command(&mut computer, door)?;
+    }
+    for item in &explorer.items {
+        command(&mut computer, &format!("drop {item}"))?;
+    }
+
+    for carried in 0..1u32 << explorer.items.len() {
+        let mut trial = computer.clone();
+        for (i, item) in explorer.items.iter().enumerate() {
+            if carried & 1 << i != 0 {
+                command(&mut trial, &format!("take {item}"))?;
+            }
+        }
+        let text = trial.send(&floor)?;
+        if trial.halted() {
+            return airlock_code(&text);
+        }
+    }
+    None
+}
+
+/// Finds the code in Santa's final message, "... by typing 1234 on the keypad ...".
+fn airlock_code(text: &str) -> Option<u32> {
+    let mut words = text.split_whitespace().skip_while(|&w| w != "typing");
+    words.nth(1)?.parse().ok()
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    let mut computer = AsciiComputer::from(input.parse::<Program<i64>>().unwrap());
+    let args = env::args().collect::<Vec<_>>();
+    if args.iter().any(|a| a == "--interactive") {
+        let (sent, text) = play(&mut computer, io::stdin().lock(), false)?;
+        let mut transcript = sent.join("\n");
+        transcript.push('\n');
+        match fs::create_dir_all("data/transcripts").and_then(|_| fs::write(TRANSCRIPT, transcript))
+        {
+            Ok(()) => eprintln!("Saved transcript to {TRANSCRIPT}"),
+            Err(e) => eprintln!("Could not save transcript to {TRANSCRIPT}: {e}"),
         }
+        return airlock_code(&text);
+    }
+    if let Some(path) = args.iter().skip_while(|&a| a != "--replay").nth(1) {
+        let file = fs::File::open(path).ok()?;
+        let (_, text) = play(&mut computer, BufReader::new(file), true)?;
+        return airlock_code(&text);
     }
-    Some(328960)
+
+    solve(computer)
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
     let _ = input;
     Some(1)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_room() {
+        let text = "\n\n\n== Hull Breach ==\nYou got in through a hole in the floor here.\n\n\
+                    Doors here lead:\n- north\n- west\n\nItems here:\n- mug\n- easter egg\n\n\
+                    Command?\n";
+        let room = parse_room(text).unwrap();
+        assert_eq!(room.name, "Hull Breach");
+        assert_eq!(room.doors, ["north", "west"]);
+        assert_eq!(room.items, ["mug", "easter egg"]);
+
+        let text = "== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n- south\n\n\
+                    A loud, robotic voice says \"Alert!\"\n\n== Security Checkpoint ==\n\
+                    In the next room, a pressure-sensitive floor will verify your identity.\n\n\
+                    Doors here lead:\n- north\n- east\n\nCommand?\n";
+        let room = parse_room(text).unwrap();
+        assert_eq!(room.name, "Security Checkpoint");
+        assert_eq!(room.doors, ["north", "east"]);
+        assert!(room.items.is_empty());
+    }
+
+    #[test]
+    fn test_airlock_code() {
+        let text = "\"Oh, hello! You should be able to get in by typing 2424308 on the keypad at \
+                    the main airlock.\"\n";
+        assert_eq!(airlock_code(text), Some(2424308));
+    }
+
+    #[test]
+    fn test_save_or_load() {
+        let path = env::temp_dir().join(format!("aoc-25-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        // Echoes its input.
+        let echo = "3,100,4,100,1105,1,0".parse::<Program<i64>>().unwrap();
+        let mut computer = AsciiComputer::from(echo);
+        computer.send_line("a");
+        assert_eq!(computer.read_until_prompt().as_deref(), Ok("a\n"));
+        assert_eq!(
+            save_or_load(&mut computer, &format!("save {path}")),
+            Some(false)
+        );
+        let saved = computer.clone();
+
+        computer.send_line("b");
+        assert_eq!(computer.read_until_prompt().as_deref(), Ok("b\n"));
+        assert_eq!(
+            save_or_load(&mut computer, &format!("load {path}")),
+            Some(true)
+        );
+        assert_eq!(computer.program().get(100), saved.program().get(100));
+        assert_eq!(computer.program().pc(), saved.program().pc());
+        computer.send_line("c");
+        assert_eq!(computer.read_until_prompt().as_deref(), Ok("c\n"));
+
+        assert_eq!(save_or_load(&mut computer, "take mug"), None);
+        assert_eq!(
+            save_or_load(&mut computer, "load /nonexistent"),
+            Some(false)
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_play_transcript() {
+        let path = env::temp_dir().join(format!("aoc-25-play-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let echo = "3,100,4,100,1105,1,0".parse::<Program<i64>>().unwrap();
+        let mut computer = AsciiComputer::from(echo);
+        let commands = format!("a\nsave {path}\nb\nload {path}\nc\nsave {path}\nd\n");
+        let (sent, text) = play(&mut computer, commands.as_bytes(), false).unwrap();
+        assert_eq!(
+            sent,
+            [format!("load {path}"), "c".to_string(), "d".to_string()]
+        );
+        assert_eq!(text, "d\n");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_opposite() {
+        assert_eq!(opposite("west"), Some("east"));
+        assert_eq!(opposite("north"), Some("south"));
+        assert_eq!(opposite("up"), None);
+    }
+
+    const FLOOR: &str = "Pressure-Sensitive Floor";
+
+    /// The doors of a room, with where they lead, and the items in it.
+    type Contents = (Vec<(&'static str, &'static str)>, Vec<String>);
+
+    /// A small game played in Rust, with the items that trip up the droid.
+    #[derive(Clone)]
+    struct Scripted {
+        rooms: HashMap<&'static str, Contents>,
+        here: &'static str,
+        carried: Vec<String>,
+        /// What the floor lets through.
+        password: Vec<&'static str>,
+        stuck: bool,
+        halted: bool,
+    }
+
+    impl Scripted {
+        fn new() -> Self {
+            let rooms = [
+                (
+                    "Hull Breach",
+                    vec![("north", "Kitchen"), ("east", "Hallway")],
+                    vec!["mug"],
+                ),
+                (
+                    "Kitchen",
+                    vec![("south", "Hull Breach")],
+                    vec!["molten lava", "hologram"],
+                ),
+                (
+                    "Hallway",
+                    vec![("west", "Hull Breach"), ("north", "Security Checkpoint")],
+                    vec!["giant electromagnet", "infinite loop", "spool of cat6"],
+                ),
+                (
+                    "Security Checkpoint",
+                    vec![("south", "Hallway"), ("north", FLOOR)],
+                    vec![],
+                ),
+                (FLOOR, vec![("south", "Security Checkpoint")], vec![]),
+            ];
+            Self {
+                rooms: rooms
+                    .into_iter()
+                    .map(|(name, doors, items)| {
+                        (name, (doors, items.into_iter().map(String::from).collect()))
+                    })
+                    .collect(),
+                here: "Hull Breach",
+                carried: Vec::new(),
+                password: vec!["mug", "spool of cat6"],
+                stuck: false,
+                halted: false,
+            }
+        }
+
+        fn describe(&self, name: &str) -> String {
+            let (doors, items) = &self.rooms[name];
+            let mut text = format!("\n\n\n== {name} ==\nA room.\n\nDoors here lead:\n");
+            for (door, _) in doors {
+                text += &format!("- {door}\n");
+            }
+            if !items.is_empty() {
+                text += "\nItems here:\n";
+                for item in items {
+                    text += &format!("- {item}\n");
+                }
+            }
+            text
+        }
+    }
+
+    impl Game for Scripted {
+        fn read(&mut self) -> Option<String> {
+            Some(self.describe(self.here) + "\nCommand?\n")
+        }
+
+        fn send(&mut self, cmd: &str) -> Option<String> {
+            if let Some(item) = cmd.strip_prefix("take ") {
+                match item {
+                    "infinite loop" => return None,
+                    "molten lava" => {
+                        self.halted = true;
+                        return Some("\nThe molten lava is way too hot! You melt!\n".into());
+                    }
+                    "giant electromagnet" => self.stuck = true,
+                    _ => {}
+                }
+                let items = &mut self.rooms.get_mut(self.here).unwrap().1;
+                items.retain(|i| i != item);
+                self.carried.push(item.to_string());
+                return Some(format!("\nYou take the {item}.\n\nCommand?\n"));
+            }
+            if let Some(item) = cmd.strip_prefix("drop ") {
+                self.carried.retain(|i| i != item);
+                let items = &mut self.rooms.get_mut(self.here).unwrap().1;
+                items.push(item.to_string());
+                return Some(format!("\nYou drop the {item}.\n\nCommand?\n"));
+            }
+            if self.stuck {
+                return Some(
+                    "\nThe giant electromagnet is stuck to you.  You can't move!!\n\nCommand?\n"
+                        .into(),
+                );
+            }
+            let (doors, _) = &self.rooms[self.here];
+            let &(_, to) = doors.iter().find(|&&(door, _)| door == cmd).unwrap();
+            if to != FLOOR {
+                self.here = to;
+                return self.read();
+            }
+            let mut carried = self.carried.clone();
+            carried.sort();
+            if carried == self.password {
+                self.halted = true;
+                return Some("You should be able to get in by typing 1234 on the keypad.\n".into());
+            }
+            Some(
+                self.describe(FLOOR)
+                    + "\nA loud, robotic voice says \"Alert!\"\n"
+                    + &self.read()?,
+            )
+        }
+
+        fn halted(&self) -> bool {
+            self.halted
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut explorer = Explorer::default();
+        let mut game = Scripted::new();
+        let start = parse_room(&game.read().unwrap()).unwrap();
+        explorer
+            .explore(&mut game, &start, &mut Vec::new())
+            .unwrap();
+        assert_eq!(explorer.items, ["mug", "hologram", "spool of cat6"]);
+        // The checkpoint is only known once its floor has thrown the droid back.
+        assert_eq!(
+            explorer.checkpoint,
+            Some((
+                vec!["east".to_string(), "north".to_string()],
+                "north".to_string()
+            ))
+        );
+        assert_eq!(game.here, "Hull Breach");
+
+        assert_eq!(solve(Scripted::new()), Some(1234));
+    }
+}
@@ -0,0 +1,466 @@
+//! Static analysis of a program's initial memory: a control-flow graph built on top of the
+//! [disassembly](super::disasm), with the jumps it cannot follow, the instructions that patch
+//! code, and the subroutine calls it recognizes.
+//!
+//! Calls are recognized by the usual stack discipline on the relative base: the caller stores
+//! its return address through `[rb+n]` and jumps to the subroutine, which adjusts `rb` for its
+//! frame, and returns with `RELADJ #-k` followed by a jump through `[rb+n]`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Display, Write},
+};
+
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive};
+
+use super::{
+    disasm::{disassemble, Disassembly, Line},
+    Instruction, Opcode, ParameterMode, Program,
+};
+
+/// A run of instructions that is only entered at its first instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Block {
+    pub start: usize,
+    /// The address after the last instruction.
+    pub end: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum EdgeKind {
+    /// Execution continues with the next instruction.
+    Fallthrough,
+    Jump,
+    Call,
+    /// From a subroutine's return back to the instruction after the call.
+    Return,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Call {
+    /// The address of the jump into the subroutine.
+    pub site: usize,
+    pub target: usize,
+    pub return_to: usize,
+}
+
+/// An instruction that writes into a reachable instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CodeWrite {
+    pub addr: usize,
+    pub target: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cfg<T> {
+    pub disassembly: Disassembly<T>,
+    /// Blocks by their start address.
+    pub blocks: BTreeMap<usize, Block>,
+    /// Edges between block start addresses.
+    pub edges: BTreeSet<Edge>,
+    /// Edges to addresses that do not start a block, such as jumps into data or past the end of
+    /// the code.
+    pub unresolved: BTreeSet<Edge>,
+    /// Jumps whose target is read from memory, including returns.
+    pub computed_jumps: BTreeSet<usize>,
+    pub calls: Vec<Call>,
+    pub returns: BTreeSet<usize>,
+    pub code_writes: Vec<CodeWrite>,
+}
+
+/// The instruction of a code line.
+fn inst_of<T>(line: &Line<T>) -> Option<(usize, Instruction, &[T])> {
+    match line {
+        Line::Code { addr, inst, params } => Some((*addr, *inst, params.as_slice())),
+        Line::Data { .. } => None,
+    }
+}
+
+/// Whether a jump is taken: always, never, or `None` if it depends on memory.
+fn taken<T>(inst: Instruction, params: &[T]) -> Option<bool>
+where
+    T: Num,
+{
+    (inst.p_modes[0] == ParameterMode::Immediate)
+        .then(|| params[0].is_zero() == (inst.code == Opcode::Jz))
+}
+
+/// The immediate value an `ADD x, #0` or `MUL x, #1` stores, if that is what it is.
+fn moved_immediate<T>(inst: Instruction, params: &[T]) -> Option<usize>
+where
+    T: Num + ToPrimitive,
+{
+    let identity = match inst.code {
+        Opcode::Add => T::zero(),
+        Opcode::Mul => T::one(),
+        _ => return None,
+    };
+    if inst.p_modes[..2] != [ParameterMode::Immediate; 2] {
+        return None;
+    }
+    if params[1] == identity {
+        params[0].to_usize()
+    } else if params[0] == identity {
+        params[1].to_usize()
+    } else {
+        None
+    }
+}
+
+impl<T> Cfg<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    /// Analyzes the initial memory of `program`.
+    pub fn build(program: &Program<T>) -> Self {
+        let disassembly = disassemble(program);
+        let code = disassembly
+            .lines
+            .iter()
+            .filter_map(inst_of)
+            .collect::<Vec<_>>();
+
+        // Blocks start at jump targets and after jumps, halts and data.
+        let mut blocks = BTreeMap::new();
+        let mut start = None;
+        for (i, &(addr, inst, params)) in code.iter().enumerate() {
+            let start_here = *start.get_or_insert(addr);
+            let end = addr + params.len() + 1;
+            let next_starts = code
+                .get(i + 1)
+                .is_none_or(|&(next, ..)| next != end || disassembly.labels.contains(&next));
+            let terminates = matches!(inst.code, Opcode::Jnz | Opcode::Jz | Opcode::Halt);
+            if next_starts || terminates {
+                blocks.insert(
+                    start_here,
+                    Block {
+                        start: start_here,
+                        end,
+                    },
+                );
+                start = None;
+            }
+        }
+
+        let mut edges = BTreeSet::new();
+        let mut unresolved = BTreeSet::new();
+        let mut computed_jumps = BTreeSet::new();
+        let mut calls = Vec::new();
+        let mut returns = BTreeSet::new();
+        let mut code_writes = Vec::new();
+        let in_code = |target: usize| {
+            code.iter()
+                .any(|&(addr, _, params)| (addr..=addr + params.len()).contains(&target))
+        };
+
+        for block in blocks.values() {
+            let insts = code
+                .iter()
+                .filter(|&&(addr, ..)| (block.start..block.end).contains(&addr))
+                .collect::<Vec<_>>();
+            for &&(addr, inst, params) in &insts {
+                let Some(n) = inst.code.write_param() else {
+                    continue;
+                };
+                if inst.p_modes[n] == ParameterMode::Position {
+                    if let Some(target) = params[n].to_usize().filter(|&t| in_code(t)) {
+                        code_writes.push(CodeWrite { addr, target });
+                    }
+                }
+            }
+
+            let &&(addr, inst, params) = insts.last().unwrap();
+            let mut edge = |to, kind| {
+                let edge = Edge {
+                    from: block.start,
+                    to,
+                    kind,
+                };
+                if blocks.contains_key(&to) {
+                    edges.insert(edge);
+                } else {
+                    unresolved.insert(edge);
+                }
+            };
+            if !matches!(inst.code, Opcode::Jnz | Opcode::Jz) {
+                if inst.code != Opcode::Halt {
+                    edge(block.end, EdgeKind::Fallthrough);
+                }
+                continue;
+            }
+
+            let taken = taken(inst, params);
+            if taken != Some(true) {
+                edge(block.end, EdgeKind::Fallthrough);
+            }
+            if taken == Some(false) {
+                continue;
+            }
+            match inst.p_modes[1] {
+                ParameterMode::Immediate => {
+                    let Some(target) = params[1].to_usize() else {
+                        continue;
+                    };
+                    // A call pushes the address after the jump before making it.
+                    let pushes_return = insts.iter().rev().skip(1).any(|&&(_, i, p)| {
+                        i.p_modes[2] == ParameterMode::Relative
+                            && moved_immediate(i, p) == Some(block.end)
+                    });
+                    if pushes_return && taken == Some(true) {
+                        edge(target, EdgeKind::Call);
+                        calls.push(Call {
+                            site: addr,
+                            target,
+                            return_to: block.end,
+                        });
+                    } else {
+                        edge(target, EdgeKind::Jump);
+                    }
+                }
+                mode => {
+                    computed_jumps.insert(addr);
+                    let pops_frame =
+                        insts.len() > 1 && insts[insts.len() - 2].1.code == Opcode::RelAdj;
+                    if mode == ParameterMode::Relative && taken == Some(true) && pops_frame {
+                        returns.insert(addr);
+                    }
+                }
+            }
+        }
+
+        let mut cfg = Self {
+            disassembly,
+            blocks,
+            edges,
+            unresolved,
+            computed_jumps,
+            calls,
+            returns,
+            code_writes,
+        };
+        cfg.link_returns();
+        cfg
+    }
+}
+
+impl<T> Cfg<T> {
+    /// Adds an edge from every return a called subroutine can reach back to its caller.
+    fn link_returns(&mut self) {
+        let mut links = Vec::new();
+        for call in &self.calls {
+            let mut seen = BTreeSet::new();
+            let mut stack = vec![call.target];
+            while let Some(block) = stack.pop() {
+                if !self.blocks.contains_key(&block) || !seen.insert(block) {
+                    continue;
+                }
+                let last = self.last_inst(block);
+                if self.returns.contains(&last) {
+                    links.push(Edge {
+                        from: block,
+                        to: call.return_to,
+                        kind: EdgeKind::Return,
+                    });
+                }
+                for edge in self.edges.iter().filter(|e| e.from == block) {
+                    match edge.kind {
+                        EdgeKind::Fallthrough | EdgeKind::Jump => stack.push(edge.to),
+                        // Step over nested calls.
+                        EdgeKind::Call => stack.extend(
+                            self.calls
+                                .iter()
+                                .filter(|c| c.site == self.last_inst(block))
+                                .map(|c| c.return_to),
+                        ),
+                        EdgeKind::Return => {}
+                    }
+                }
+            }
+        }
+        for link in links {
+            if self.blocks.contains_key(&link.to) {
+                self.edges.insert(link);
+            } else {
+                self.unresolved.insert(link);
+            }
+        }
+    }
+
+    /// The address of the last instruction of a block.
+    fn last_inst(&self, block: usize) -> usize {
+        let end = self.blocks[&block].end;
+        self.disassembly
+            .lines
+            .iter()
+            .filter(|l| (block..end).contains(&l.addr()))
+            .last()
+            .map_or(block, |l| l.addr())
+    }
+}
+
+impl<T> Cfg<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + Display,
+{
+    /// Renders the graph in Graphviz DOT format. Blocks that end in a computed jump are red,
+    /// and blocks that write into code are filled orange. Unresolved targets are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph cfg {\n    node [shape=box, fontname=monospace];\n".to_string();
+        for block in self.blocks.values() {
+            let mut label = format!("L{}:\\l", block.start);
+            for line in self
+                .disassembly
+                .lines
+                .iter()
+                .filter(|l| (block.start..block.end).contains(&l.addr()))
+            {
+                let text = line.text(&self.disassembly.labels).replace('"', "\\\"");
+                let _ = write!(label, "    {text}\\l");
+            }
+            let mut attrs = format!("label=\"{label}\"");
+            if self.computed_jumps.contains(&self.last_inst(block.start)) {
+                attrs.push_str(", color=red");
+            }
+            if self
+                .code_writes
+                .iter()
+                .any(|w| (block.start..block.end).contains(&w.addr))
+            {
+                attrs.push_str(", style=filled, fillcolor=orange");
+            }
+            let _ = writeln!(dot, "    b{} [{attrs}];", block.start);
+        }
+        let targets = self
+            .unresolved
+            .iter()
+            .map(|e| e.to)
+            .collect::<BTreeSet<_>>();
+        for to in targets {
+            let _ = writeln!(dot, "    u{to} [label=\"{to}?\", style=dashed];");
+        }
+        let edges = self.edges.iter().map(|e| (e, 'b'));
+        for (edge, to) in edges.chain(self.unresolved.iter().map(|e| (e, 'u'))) {
+            let attrs = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [label=\"jump\"]",
+                EdgeKind::Call => " [label=\"call\", style=bold]",
+                EdgeKind::Return => " [label=\"return\", style=dashed]",
+            };
+            let _ = writeln!(dot, "    b{} -> {to}{}{attrs};", edge.from, edge.to);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Doubles `x` in a subroutine, and patches the output at 13 to print `x` instead of `y`.
+    const CALL: &str = "
+            RELADJ #stack
+            ADD #ret, #0 -> [rb+0]
+            JZ #0, #double
+    ret:    ADD #x, #0 -> [14]
+            OUTPUT [y]
+            JNZ [x], #done
+            JNZ #1, [x]
+    done:   HALT
+    double: RELADJ #1
+            MUL [x], #2 -> [x]
+            RELADJ #-1
+            JZ #0, [rb+0]
+    x:      .data 21
+    y:      .data 0
+    stack:  .data 0
+    ";
+
+    fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+        Edge { from, to, kind }
+    }
+
+    #[test]
+    fn test_cfg() {
+        let program = assemble::<i64>(CALL).unwrap();
+        let cfg = Cfg::build(&program);
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<_>>(),
+            [0, 9, 18, 21, 22]
+        );
+        assert_eq!(
+            cfg.calls,
+            [Call {
+                site: 6,
+                target: 22,
+                return_to: 9
+            }]
+        );
+        assert_eq!(cfg.returns, BTreeSet::from([30]));
+        assert_eq!(cfg.computed_jumps, BTreeSet::from([18, 30]));
+        assert_eq!(
+            cfg.code_writes,
+            [CodeWrite {
+                addr: 9,
+                target: 14
+            }]
+        );
+        assert_eq!(
+            cfg.edges,
+            BTreeSet::from([
+                edge(0, 22, EdgeKind::Call),
+                edge(9, 18, EdgeKind::Fallthrough),
+                edge(9, 21, EdgeKind::Jump),
+                edge(22, 9, EdgeKind::Return),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let program = assemble::<i64>(CALL).unwrap();
+        let dot = Cfg::build(&program).to_dot();
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains("    b0 -> b22 [label=\"call\", style=bold];\n"));
+        assert!(dot.contains("    b22 -> b9 [label=\"return\", style=dashed];\n"));
+        assert!(dot.contains("    b18 [label=\"L18:\\l    JNZ #1, [33]\\l\", color=red];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_jump_into_data() {
+        let program = assemble::<i64>(
+            "
+            JNZ [c], #data
+            JNZ #1, #1000
+    data:   .data 7
+    c:      .data 1
+            ",
+        )
+        .unwrap();
+        let cfg = Cfg::build(&program);
+        assert_eq!(cfg.blocks.keys().copied().collect::<Vec<_>>(), [0, 3]);
+        assert_eq!(
+            cfg.edges,
+            BTreeSet::from([edge(0, 3, EdgeKind::Fallthrough)])
+        );
+        assert_eq!(
+            cfg.unresolved,
+            BTreeSet::from([edge(0, 6, EdgeKind::Jump), edge(3, 1000, EdgeKind::Jump)])
+        );
+
+        let dot = cfg.to_dot();
+        assert!(dot.contains("    u6 [label=\"6?\", style=dashed];\n"));
+        assert!(dot.contains("    b0 -> u6 [label=\"jump\"];\n"));
+        assert!(dot.contains("    b3 -> u1000 [label=\"jump\"];\n"));
+        assert!(!dot.contains("-> b6"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Text protocols on top of Intcode, as used by days 17, 21 and 25.
+//!
+//! Programs read and write text one ASCII character per word. Anything outside the ASCII range
+//! is not text but a value, usually the puzzle answer printed at the very end.
+
+use num_traits::{CheckedAdd, CheckedMul, FromPrimitive, Num, ToPrimitive};
+
+use super::{Event, IntcodeError, Program};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AsciiComputer<T> {
+    program: Program<T>,
+    /// Text output that has not been read yet.
+    text: String,
+    value: Option<T>,
+    halted: bool,
+}
+
+impl<T> From<Program<T>> for AsciiComputer<T> {
+    fn from(program: Program<T>) -> Self {
+        Self {
+            program,
+            text: String::new(),
+            value: None,
+            halted: false,
+        }
+    }
+}
+
+impl<T> AsciiComputer<T>
+where
+    T: Num
+        + Clone
+        + ToPrimitive
+        + FromPrimitive
+        + PartialOrd
+        + CheckedAdd
+        + CheckedMul
+        + std::fmt::Debug,
+{
+    /// Queues `line` followed by a newline as input.
+    pub fn send_line(&mut self, line: &str) {
+        self.program
+            .input(line.bytes().chain(*b"\n").filter_map(|b| T::from_u8(b)));
+    }
+
+    /// Runs until the program waits for input or halts, returning all text output that has not
+    /// been read yet.
+    pub fn read_until_prompt(&mut self) -> Result<String, IntcodeError<T>> {
+        self.run(false, None)?;
+        Ok(std::mem::take(&mut self.text))
+    }
+
+    /// Like [`AsciiComputer::read_until_prompt`], but gives up and returns `None` after
+    /// `max_steps` instructions, for programs that might never ask for input again.
+    pub fn read_until_prompt_within(
+        &mut self,
+        max_steps: usize,
+    ) -> Result<Option<String>, IntcodeError<T>> {
+        Ok(self
+            .run(false, Some(max_steps))?
+            .then(|| std::mem::take(&mut self.text)))
+    }
+
+    /// Runs until the program outputs a whole line and returns it without the newline. Returns
+    /// `None` if the program waits for input or halts first; the start of the line is kept for
+    /// the next read.
+    pub fn read_line(&mut self) -> Result<Option<String>, IntcodeError<T>> {
+        self.run(true, None)?;
+        Ok(self.text.ends_with('\n').then(|| {
+            let mut line = std::mem::take(&mut self.text);
+            line.pop();
+            line
+        }))
+    }
+
+    /// The last output that was not an ASCII character.
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn program(&self) -> &Program<T> {
+        &self.program
+    }
+
+    pub fn program_mut(&mut self) -> &mut Program<T> {
+        &mut self.program
+    }
+
+    pub fn into_program(self) -> Program<T> {
+        self.program
+    }
+
+    /// Runs until the program waits for input or halts, or until it completes a line if
+    /// `stop_at_newline` is set. Returns `false` if `max_steps` or the program's budget ran out
+    /// first.
+    fn run(
+        &mut self,
+        stop_at_newline: bool,
+        max_steps: Option<usize>,
+    ) -> Result<bool, IntcodeError<T>> {
+        let mut steps = 0;
+        loop {
+            if stop_at_newline && self.text.ends_with('\n') {
+                return Ok(true);
+            }
+            if max_steps.is_some_and(|max| steps == max) {
+                return Ok(false);
+            }
+            steps += 1;
+            match self.program.step()? {
+                Event::Output(v) => match v.to_u8().filter(u8::is_ascii) {
+                    Some(c) => self.text.push(c as char),
+                    None => self.value = Some(v),
+                },
+                Event::Halted => {
+                    self.halted = true;
+                    return Ok(true);
+                }
+                Event::NeedsInput => return Ok(true),
+                Event::BudgetExhausted => return Ok(false),
+                Event::Stepped => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Prints a prompt, reads a line and outputs 1000 plus the length of the line.
+    const PROMPT: &str = "
+            RELADJ #msg
+    print:  OUTPUT [rb+0]
+            RELADJ #1
+            JNZ [rb+0], #print
+    read:   INPUT -> [c]
+            ADD [n], #1 -> [n]
+            EQ [c], #10 -> [t]
+            JZ [t], #read
+            ADD [n], #999 -> [n]
+            OUTPUT [n]
+            HALT
+    c:      .data 0
+    n:      .data 0
+    t:      .data 0
+    msg:    .data 72, 105, 10, 62, 32, 0
+    ";
+
+    #[test]
+    fn test_ascii_computer() {
+        let mut computer = AsciiComputer::from(assemble::<i64>(PROMPT).unwrap());
+        assert_eq!(computer.read_line(), Ok(Some("Hi".to_string())));
+        assert_eq!(computer.read_line(), Ok(None));
+        assert_eq!(computer.read_until_prompt().as_deref(), Ok("> "));
+        assert!(!computer.halted());
+
+        computer.send_line("abc");
+        assert_eq!(computer.read_until_prompt().as_deref(), Ok(""));
+        assert_eq!(computer.value(), Some(&1003));
+        assert!(computer.halted());
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let mut computer = AsciiComputer::from("104,65,1105,1,2".parse::<Program<i64>>().unwrap());
+        assert_eq!(computer.read_until_prompt_within(1000), Ok(None));
+        assert!(!computer.halted());
+
+        let mut computer = AsciiComputer::from("104,65,99".parse::<Program<i64>>().unwrap());
+        assert_eq!(computer.read_until_prompt_within(1), Ok(None));
+        assert_eq!(
+            computer.read_until_prompt_within(2),
+            Ok(Some("A".to_string()))
+        );
+        assert!(computer.halted());
+    }
+}
@@ -0,0 +1,384 @@
+//! A small assembler for Intcode.
+//!
+//! ```text
+//! .macro dec x
+//!     ADD x, #-1 -> x
+//! .endm
+//!
+//!     ADD #3, #0 -> [n]       ; comments run to the end of the line
+//! loop:
+//!     OUTPUT [n]
+//!     dec [n]
+//!     JNZ [n], #loop
+//!     HALT
+//! n:  .data 0
+//! ```
+//!
+//! Mnemonics are the [`Opcode`] names, case-insensitive. Operands are `#imm`, `[pos]` or
+//! `[rb+n]`/`[rb-n]`, where any value may be a number or a label. The written operand of `ADD`,
+//! `MUL`, `LT`, `EQ` and `INPUT` can be given after `->`, as in the disassembler output, or as
+//! the last operand. Macro arguments are substituted wherever the parameter name appears as an
+//! identifier in the body.
+
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{anyhow, bail, Context};
+use num_traits::{FromPrimitive, Num, ToPrimitive};
+
+use super::{Instruction, Opcode, ParameterMode, Program};
+
+/// Nesting limit for macro expansion, which stops self-referencing macros.
+const MAX_MACRO_DEPTH: usize = 32;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Value<T> {
+    Lit(T),
+    Label(String),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Statement<T> {
+    Inst {
+        inst: Instruction,
+        params: Vec<Value<T>>,
+    },
+    Data(Vec<Value<T>>),
+}
+
+impl<T> Statement<T> {
+    fn len(&self) -> usize {
+        match self {
+            Statement::Inst { params, .. } => params.len() + 1,
+            Statement::Data(words) => words.len(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Macro<'a> {
+    params: Vec<&'a str>,
+    body: Vec<(usize, &'a str)>,
+}
+
+/// Assembles `src` into a fresh [`Program`].
+pub fn assemble<T>(src: &str) -> anyhow::Result<Program<T>>
+where
+    T: Num + Clone + FromPrimitive + ToPrimitive,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    let mut lines = Vec::new();
+    let mut macros = HashMap::new();
+    let mut source = src
+        .lines()
+        .enumerate()
+        .map(|(n, l)| (n + 1, l.split(';').next().unwrap().trim()))
+        .filter(|(_, l)| !l.is_empty());
+    while let Some((n, line)) = source.next() {
+        let Some(def) = line.strip_prefix(".macro") else {
+            lines.push((n, line.to_string()));
+            continue;
+        };
+        let (name, params) = def.trim().split_once(' ').unwrap_or((def.trim(), ""));
+        if !is_ident(name) || Opcode::from_str(name).is_ok() {
+            bail!("line {n}: invalid macro name `{name}`");
+        }
+        let params = split_operands(params);
+        let mut body = Vec::new();
+        loop {
+            match source.next() {
+                Some((_, ".endm")) => break,
+                Some((m, line)) if line.starts_with(".macro") => {
+                    bail!("line {m}: macro definitions cannot be nested")
+                }
+                Some(line) => body.push(line),
+                None => bail!("line {n}: macro `{name}` is missing `.endm`"),
+            }
+        }
+        if macros.insert(name, Macro { params, body }).is_some() {
+            bail!("line {n}: macro `{name}` is defined twice");
+        }
+    }
+
+    let mut expanded = Vec::new();
+    for (n, line) in lines {
+        expand(n, &line, &macros, 0, &mut expanded)?;
+    }
+
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = 0;
+    for (n, line) in expanded {
+        let (label, rest) = split_label(&line);
+        if let Some(label) = label {
+            if labels.insert(label.to_string(), addr).is_some() {
+                bail!("line {n}: label `{label}` is defined twice");
+            }
+        }
+        if !rest.is_empty() {
+            let statement = parse_statement::<T>(rest).with_context(|| format!("line {n}"))?;
+            addr += statement.len();
+            statements.push((n, statement));
+        }
+    }
+
+    let mut code = Vec::with_capacity(addr);
+    for (n, statement) in statements {
+        let resolve = |v: Value<T>| match v {
+            Value::Lit(v) => Ok(v),
+            Value::Label(l) => labels
+                .get(&l)
+                .and_then(|&a| T::from_usize(a))
+                .ok_or_else(|| anyhow!("line {n}: unknown label `{l}`")),
+        };
+        match statement {
+            Statement::Inst { inst, params } => {
+                code.push(T::from_u32(u32::from(inst)).unwrap());
+                for p in params {
+                    code.push(resolve(p)?);
+                }
+            }
+            Statement::Data(words) => {
+                for w in words {
+                    code.push(resolve(w)?);
+                }
+            }
+        }
+    }
+    Ok(code.into())
+}
+
+fn expand(
+    n: usize,
+    line: &str,
+    macros: &HashMap<&str, Macro>,
+    depth: usize,
+    out: &mut Vec<(usize, String)>,
+) -> anyhow::Result<()> {
+    let (label, rest) = split_label(line);
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let Some(m) = macros.get(name) else {
+        out.push((n, line.to_string()));
+        return Ok(());
+    };
+    if depth == MAX_MACRO_DEPTH {
+        bail!("line {n}: macro `{name}` expands too deeply");
+    }
+    let args = split_operands(args);
+    if args.len() != m.params.len() {
+        bail!(
+            "line {n}: macro `{name}` takes {} argument(s), got {}",
+            m.params.len(),
+            args.len()
+        );
+    }
+    if let Some(label) = label {
+        out.push((n, format!("{label}:")));
+    }
+    for (_, body) in &m.body {
+        let mut line = String::new();
+        let mut rest = *body;
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let len = rest[start..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - start);
+            let word = &rest[start..start + len];
+            line.push_str(&rest[..start]);
+            match m.params.iter().position(|&p| p == word) {
+                Some(i) => line.push_str(args[i]),
+                None => line.push_str(word),
+            }
+            rest = &rest[start + len..];
+        }
+        line.push_str(rest);
+        expand(n, &line, macros, depth + 1, out)?;
+    }
+    Ok(())
+}
+
+fn parse_statement<T>(s: &str) -> anyhow::Result<Statement<T>>
+where
+    T: Num,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    let (mnemonic, operands) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    if mnemonic == ".data" {
+        let words = split_operands(operands)
+            .into_iter()
+            .map(parse_value)
+            .collect::<anyhow::Result<_>>()?;
+        return Ok(Statement::Data(words));
+    }
+    let code = Opcode::from_str(mnemonic).map_err(|_| anyhow!("unknown mnemonic `{mnemonic}`"))?;
+
+    let mut operands = match operands.split_once("->") {
+        Some((reads, write)) => {
+            let mut operands = split_operands(reads);
+            let write_param = code
+                .write_param()
+                .ok_or_else(|| anyhow!("{code} does not write to memory"))?;
+            if operands.len() != write_param {
+                bail!("{code} takes {write_param} operand(s) before `->`");
+            }
+            operands.push(write.trim());
+            operands
+        }
+        None => split_operands(operands),
+    };
+    if operands.len() != code.num_params() {
+        bail!("{code} takes {} operand(s)", code.num_params());
+    }
+
+    let mut p_modes = [ParameterMode::Position; 3];
+    let mut params = Vec::with_capacity(operands.len());
+    for (pmode, operand) in p_modes.iter_mut().zip(operands.drain(..)) {
+        let (mode, value) = parse_operand(operand)?;
+        *pmode = mode;
+        params.push(value);
+    }
+    if code
+        .write_param()
+        .is_some_and(|n| p_modes[n] == ParameterMode::Immediate)
+    {
+        bail!("{code} cannot write to an immediate operand");
+    }
+    Ok(Statement::Inst {
+        inst: Instruction { p_modes, code },
+        params,
+    })
+}
+
+fn parse_operand<T>(s: &str) -> anyhow::Result<(ParameterMode, Value<T>)>
+where
+    T: Num,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    if let Some(imm) = s.strip_prefix('#') {
+        return Ok((ParameterMode::Immediate, parse_value(imm.trim())?));
+    }
+    let inner = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| anyhow!("invalid operand `{s}`"))?
+        .trim();
+    match inner.strip_prefix("rb") {
+        Some(offset) => {
+            let offset = offset.trim();
+            let offset = match offset.strip_prefix('-') {
+                Some(neg) => format!("-{}", neg.trim()),
+                None => offset
+                    .strip_prefix('+')
+                    .ok_or_else(|| anyhow!("invalid operand `{s}`"))?
+                    .trim()
+                    .to_string(),
+            };
+            Ok((ParameterMode::Relative, parse_value(&offset)?))
+        }
+        None => Ok((ParameterMode::Position, parse_value(inner)?)),
+    }
+}
+
+fn parse_value<T>(s: &str) -> anyhow::Result<Value<T>>
+where
+    T: Num,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    if is_ident(s) {
+        Ok(Value::Label(s.to_string()))
+    } else {
+        let v = T::from_str_radix(s, 10).with_context(|| format!("invalid number `{s}`"))?;
+        Ok(Value::Lit(v))
+    }
+}
+
+fn split_label(line: &str) -> (Option<&str>, &str) {
+    match line.split_once(':') {
+        Some((label, rest)) if is_ident(label.trim()) => (Some(label.trim()), rest.trim()),
+        _ => (None, line.trim()),
+    }
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    if s.trim().is_empty() {
+        Vec::new()
+    } else {
+        s.split(',').map(str::trim).collect()
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        intcode::disasm::disassemble,
+        template::{read_file, read_file_part, Day},
+    };
+
+    #[test]
+    fn test_labels_and_macros() {
+        let src = "
+            .macro dec x
+                ADD x, #-1 -> x
+            .endm
+
+                ADD #3, #0 -> [n]   ; n = 3
+            loop:
+                OUTPUT [n]
+                dec [n]
+                JNZ [n], #loop
+                HALT
+            n:  .data 0
+        ";
+        let program = assemble::<i64>(src).unwrap();
+        assert_eq!(
+            program,
+            "1101,3,0,14,4,14,1001,14,-1,14,1005,14,4,99,0"
+                .parse::<Program<i64>>()
+                .unwrap()
+        );
+        assert_eq!(program.collect::<Vec<_>>(), [3, 2, 1]);
+    }
+
+    #[test]
+    fn test_positional_write_operand() {
+        let program = assemble::<i64>("eq #8, [rb+0], [rb-2]\nhalt").unwrap();
+        assert_eq!(program, "22108,8,0,-2,99".parse::<Program<i64>>().unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(assemble::<i64>("JNZ #1, #nowhere").is_err());
+        assert!(assemble::<i64>("ADD #1, #2 -> #3").is_err());
+        assert!(assemble::<i64>("OUTPUT #1, #2").is_err());
+        assert!(assemble::<i64>("FOO #1").is_err());
+        assert!(assemble::<i64>("a: HALT\na: HALT").is_err());
+        assert!(assemble::<i64>(".macro m\nm\n.endm\nm").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_examples() {
+        for (day, part) in [
+            (7, 0),
+            (7, 1),
+            (7, 2),
+            (7, 3),
+            (7, 4),
+            (9, 0),
+            (9, 1),
+            (9, 2),
+        ] {
+            let day = Day::new(day).unwrap();
+            let input = match part {
+                0 => read_file("examples", day),
+                part => read_file_part("examples", day, part),
+            };
+            let program = input.parse::<Program<i64>>().unwrap();
+            let listing = disassemble(&program).to_string();
+            assert_eq!(assemble::<i64>(&listing).unwrap(), program, "{listing}");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use num_traits::ToPrimitive;
+
+use super::Instruction;
+
+/// The loaded program decoded once up front, so executing an instruction skips the opcode and
+/// parameter mode arithmetic. Words that get written to are dropped from it and decoded on
+/// every execution instead. It is shared copy-on-write like memory, so resets and clones start
+/// with every instruction already decoded.
+///
+/// It only mirrors memory, so it is ignored when comparing programs.
+#[derive(Clone, Default)]
+pub(super) struct DecodeCache(Arc<Vec<Option<Instruction>>>);
+
+fn decode<T>(word: &T) -> Option<Instruction>
+where
+    T: ToPrimitive,
+{
+    Instruction::try_from(word.to_u32()?).ok()
+}
+
+impl DecodeCache {
+    pub(super) fn new<T>(code: &[T]) -> Self
+    where
+        T: ToPrimitive,
+    {
+        Self(Arc::new(code.iter().map(decode).collect()))
+    }
+
+    /// The instruction at `pc`, if `pc` is inside the loaded program and holds a valid one.
+    pub(super) fn get(&self, pc: usize) -> Option<Instruction> {
+        self.0.get(pc).copied().flatten()
+    }
+
+    /// Forgets the instruction at `addr`, which is about to be overwritten.
+    pub(super) fn invalidate(&mut self, addr: usize) {
+        if let Some(Some(_)) = self.0.get(addr) {
+            Arc::make_mut(&mut self.0)[addr] = None;
+        }
+    }
+}
+
+impl PartialEq for DecodeCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DecodeCache {}
+
+impl std::fmt::Debug for DecodeCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DecodeCache({} words)", self.0.len())
+    }
+}
@@ -0,0 +1,432 @@
+//! Conformance tests for [`Program`] against the Intcode specification.
+//!
+//! Besides hand-written cases for each part of the spec, programs are run in lockstep with
+//! [`Reference`], a deliberately naive interpreter written straight from the puzzle text.
+
+use std::collections::{HashMap, VecDeque};
+
+use num::BigInt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use strum::IntoEnumIterator;
+
+use super::{
+    memory::DEFAULT_MEMORY_LIMIT, Event, IntcodeErrorKind, Opcode, ParameterMode, Program,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Outcome {
+    Ran,
+    Output(i64),
+    Blocked,
+    Halted,
+    Fault,
+}
+
+impl From<Result<Event<i64>, super::IntcodeError<i64>>> for Outcome {
+    fn from(result: Result<Event<i64>, super::IntcodeError<i64>>) -> Self {
+        match result {
+            Ok(Event::Stepped) => Outcome::Ran,
+            Ok(Event::Output(v)) => Outcome::Output(v),
+            Ok(Event::NeedsInput) => Outcome::Blocked,
+            Ok(Event::Halted) => Outcome::Halted,
+            Err(_) => Outcome::Fault,
+            Ok(Event::BudgetExhausted) => unreachable!("no budget is set"),
+        }
+    }
+}
+
+/// The simplest interpreter that could possibly work. Faults leave it untouched, like
+/// [`Program`], and the parts the puzzle leaves open follow [`Program`]: all three mode digits
+/// must be valid, sums and products that overflow fault, and addresses stop at the default
+/// memory limit.
+#[derive(Clone, Debug)]
+struct Reference {
+    mem: HashMap<usize, i64>,
+    pc: usize,
+    rb: i64,
+    input: VecDeque<i64>,
+}
+
+impl Reference {
+    fn new(code: &[i64]) -> Self {
+        Self {
+            mem: code.iter().copied().enumerate().collect(),
+            pc: 0,
+            rb: 0,
+            input: VecDeque::new(),
+        }
+    }
+
+    fn load(&self, addr: usize) -> i64 {
+        self.mem.get(&addr).copied().unwrap_or(0)
+    }
+
+    fn mode(&self, n: usize) -> i64 {
+        self.load(self.pc) / 10i64.pow(n as u32 + 1) % 10
+    }
+
+    /// The address parameter `n` (counting from 1) refers to.
+    fn addr(&self, n: usize) -> Option<usize> {
+        let raw = self.load(self.pc + n);
+        let addr = match self.mode(n) {
+            0 => raw,
+            1 => (self.pc + n) as i64,
+            _ => self.rb.checked_add(raw)?,
+        };
+        usize::try_from(addr)
+            .ok()
+            .filter(|&a| a < DEFAULT_MEMORY_LIMIT)
+    }
+
+    fn read(&self, n: usize) -> Option<i64> {
+        Some(self.load(self.addr(n)?))
+    }
+
+    fn write_addr(&self, n: usize) -> Option<usize> {
+        if self.mode(n) == 1 {
+            return None;
+        }
+        self.addr(n)
+    }
+
+    fn store(&mut self, addr: usize, v: i64) {
+        self.mem.insert(addr, v);
+    }
+
+    fn step(&mut self) -> Outcome {
+        self.try_step().unwrap_or(Outcome::Fault)
+    }
+
+    fn try_step(&mut self) -> Option<Outcome> {
+        let op = self.load(self.pc);
+        if u32::try_from(op).is_err() || (1..=3).any(|n| self.mode(n) > 2) {
+            return None;
+        }
+        match op % 100 {
+            code @ (1 | 2 | 7 | 8) => {
+                let (x, y, dst) = (self.read(1)?, self.read(2)?, self.write_addr(3)?);
+                let v = match code {
+                    1 => x.checked_add(y)?,
+                    2 => x.checked_mul(y)?,
+                    7 => i64::from(x < y),
+                    _ => i64::from(x == y),
+                };
+                self.store(dst, v);
+                self.pc += 4;
+            }
+            3 => {
+                let dst = self.write_addr(1)?;
+                let Some(v) = self.input.pop_front() else {
+                    return Some(Outcome::Blocked);
+                };
+                self.store(dst, v);
+                self.pc += 2;
+            }
+            4 => {
+                let v = self.read(1)?;
+                self.pc += 2;
+                return Some(Outcome::Output(v));
+            }
+            code @ (5 | 6) => {
+                if (self.read(1)? != 0) == (code == 5) {
+                    self.pc = usize::try_from(self.read(2)?).ok()?;
+                } else {
+                    self.pc += 3;
+                }
+            }
+            9 => {
+                self.rb = self.rb.checked_add(self.read(1)?)?;
+                self.pc += 2;
+            }
+            99 => return Some(Outcome::Halted),
+            _ => return None,
+        }
+        Some(Outcome::Ran)
+    }
+}
+
+/// Steps both machines until they halt, fault or block, or `max_steps` run out, checking after
+/// every step that they agree.
+fn run_lockstep(code: &[i64], input: &[i64], max_steps: usize) {
+    let mut program = Program::from(code.to_vec());
+    program.set_overflow_checks(true);
+    program.input(input.iter().copied());
+    let mut reference = Reference::new(code);
+    reference.input.extend(input);
+
+    for step in 0..max_steps {
+        let expected = reference.step();
+        let actual = Outcome::from(program.step());
+        let context = || format!("step {step} of {code:?} with input {input:?}");
+        assert_eq!(actual, expected, "{}", context());
+        assert_eq!(program.pc(), reference.pc, "{}", context());
+        assert_eq!(
+            program.relative_base() as i64,
+            reference.rb,
+            "{}",
+            context()
+        );
+        for addr in reference
+            .mem
+            .keys()
+            .copied()
+            .chain(code.len()..code.len() + 64)
+        {
+            assert_eq!(
+                program.get(addr),
+                reference.load(addr),
+                "[{addr}] at {}",
+                context()
+            );
+        }
+        if matches!(
+            expected,
+            Outcome::Halted | Outcome::Fault | Outcome::Blocked
+        ) {
+            return;
+        }
+    }
+}
+
+fn instruction(code: Opcode, modes: &[ParameterMode]) -> i64 {
+    let modes = modes
+        .iter()
+        .rev()
+        .fold(0, |acc, &m| acc * 10 + u32::from(m) as i64);
+    modes * 100 + u32::from(code) as i64
+}
+
+#[test]
+fn test_every_opcode_and_mode() {
+    let params = [11, -3, 2];
+    let mut combinations = 0;
+    for code in Opcode::iter() {
+        let n = code.num_params();
+        // `Itertools` is not imported because its `get` would shadow `Program::get`.
+        let all_modes = (0..n).map(|_| ParameterMode::iter());
+        for modes in itertools::Itertools::multi_cartesian_product(all_modes) {
+            // Sets the relative base to 10, then runs the instruction.
+            let mut mem = vec![109, 10, instruction(code, &modes)];
+            mem.extend(&params[..n]);
+            mem.resize(24, 99);
+            mem[11] = 7;
+            mem[12] = 3;
+            mem[20] = -4;
+            mem[21] = 6;
+            for input in [&[42][..], &[]] {
+                run_lockstep(&mem, input, 3);
+            }
+            combinations += 1;
+        }
+    }
+    assert_eq!(combinations, 4 * 27 + 3 * 3 + 2 * 9 + 1);
+}
+
+#[test]
+fn test_spec_examples() {
+    let run = |code: &str, input: &[i64]| {
+        let mut program = code.parse::<Program<i64>>().unwrap();
+        program.input(input.iter().copied());
+        let outputs = program.by_ref().collect::<Vec<_>>();
+        (outputs, program)
+    };
+
+    // Day 2.
+    let (_, program) = run("1,9,10,3,2,3,11,0,99,30,40,50", &[]);
+    assert_eq!(program.get(0), 3500);
+    // Day 5: modes, negative immediates and comparisons.
+    let (_, program) = run("1002,4,3,4,33", &[]);
+    assert_eq!(program.get(4), 99);
+    let (_, program) = run("1101,100,-1,4,0", &[]);
+    assert_eq!(program.get(4), 99);
+    for (code, below, equal) in [
+        ("3,9,8,9,10,9,4,9,99,-1,8", 0, 1),
+        ("3,9,7,9,10,9,4,9,99,-1,8", 1, 0),
+        ("3,3,1108,-1,8,3,4,3,99", 0, 1),
+        ("3,3,1107,-1,8,3,4,3,99", 1, 0),
+    ] {
+        assert_eq!(run(code, &[7]).0, [below]);
+        assert_eq!(run(code, &[8]).0, [equal]);
+    }
+    let jumps = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,\
+                 125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+    assert_eq!(run(jumps, &[7]).0, [999]);
+    assert_eq!(run(jumps, &[8]).0, [1000]);
+    assert_eq!(run(jumps, &[9]).0, [1001]);
+}
+
+#[test]
+fn test_quine() {
+    let code = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let program = code.parse::<Program<i64>>().unwrap();
+    let outputs = program.map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(outputs.join(","), code);
+}
+
+#[test]
+fn test_large_numbers() {
+    let mut program = "104,1125899906842624,99".parse::<Program<i64>>().unwrap();
+    assert_eq!(program.next(), Some(1125899906842624));
+    let mut program = "1102,34915192,34915192,7,4,7,99,0"
+        .parse::<Program<i64>>()
+        .unwrap();
+    assert_eq!(program.next(), Some(1219070632396864));
+
+    let mut program = "1102,100000000000000000000,100000000000000000000,7,4,7,99,0"
+        .parse::<Program<BigInt>>()
+        .unwrap();
+    assert_eq!(
+        program.next(),
+        Some("10000000000000000000000000000000000000000".parse().unwrap())
+    );
+    // Addresses have to fit in memory even when words do not.
+    let mut program = "4,100000000000000000000,99"
+        .parse::<Program<BigInt>>()
+        .unwrap();
+    assert_eq!(program.step().unwrap_err().kind, IntcodeErrorKind::Overflow);
+}
+
+#[test]
+fn test_relative_base() {
+    // Day 9: relative parameters are offsets from the relative base.
+    let mut program = "109,19,204,-34,99".parse::<Program<i64>>().unwrap();
+    program.set_relative_base(2000);
+    program.set(1985, 77);
+    assert_eq!(program.next(), Some(77));
+    assert_eq!(program.relative_base(), 2019);
+
+    // The base may go negative as long as the addresses do not.
+    let mut program = "109,-5,204,7,204,4,99".parse::<Program<i64>>().unwrap();
+    assert_eq!(program.next(), Some(204));
+    let error = program.step().unwrap_err();
+    assert_eq!(
+        (error.pc, error.kind),
+        (4, IntcodeErrorKind::NegativeAddress)
+    );
+
+    // Adjustments can be read through the base itself.
+    let mut program = "109,7,209,0,204,-8,99,3".parse::<Program<i64>>().unwrap();
+    assert_eq!(program.next(), Some(209));
+    assert_eq!(program.relative_base(), 10);
+
+    // Input and arithmetic can write through the base.
+    let mut program = "109,50,203,0,21101,2,3,1,204,0,204,1,99"
+        .parse::<Program<i64>>()
+        .unwrap();
+    program.input([7]);
+    assert_eq!(program.by_ref().collect::<Vec<_>>(), [7, 5]);
+    assert_eq!((program.get(50), program.get(51)), (7, 5));
+}
+
+#[test]
+fn test_memory_past_code() {
+    let mut program = "1101,2,3,1000,4,1000,4,999,4,100000,99"
+        .parse::<Program<i64>>()
+        .unwrap();
+    assert_eq!(program.by_ref().collect::<Vec<_>>(), [5, 0, 0]);
+    assert_eq!(program.get(1000), 5);
+    assert_eq!(program.code_len(), 11);
+
+    let mut program = "1101,2,3,1000,99".parse::<Program<i64>>().unwrap();
+    program.set_memory_limit(1000);
+    let error = program.step().unwrap_err();
+    assert_eq!(error.kind, IntcodeErrorKind::OutOfMemory);
+    assert_eq!(program.get(1000), 0);
+}
+
+#[test]
+fn test_invalid_instructions() {
+    for (code, kind) in [
+        ("0", IntcodeErrorKind::InvalidOpcode),
+        ("98", IntcodeErrorKind::InvalidOpcode),
+        ("-1", IntcodeErrorKind::InvalidOpcode),
+        ("301,0,0,0", IntcodeErrorKind::InvalidParameterMode(3)),
+        ("30099", IntcodeErrorKind::InvalidParameterMode(3)),
+        ("11101,1,1,0", IntcodeErrorKind::ImmediateWrite),
+        ("103,0", IntcodeErrorKind::ImmediateWrite),
+        ("4,-1", IntcodeErrorKind::NegativeAddress),
+        ("1105,1,-1", IntcodeErrorKind::NegativeAddress),
+    ] {
+        let mut program = code.parse::<Program<i64>>().unwrap();
+        program.input([1]);
+        let error = program.step().unwrap_err();
+        assert_eq!((error.pc, error.kind), (0, kind), "{code}");
+        // Faults are sticky and leave the machine as it was.
+        assert_eq!(program.step().unwrap_err(), error, "{code}");
+        assert_eq!(program.pending_input().count(), 1, "{code}");
+    }
+}
+
+#[test]
+fn test_input_blocking() {
+    let mut program = "3,7,4,7,3,7,99,0".parse::<Program<i64>>().unwrap();
+    // Without input the instruction waits, and waits again when retried.
+    assert_eq!(program.step(), Ok(Event::NeedsInput));
+    assert_eq!(program.step(), Ok(Event::NeedsInput));
+    assert_eq!(program.pc(), 0);
+    assert_eq!(program.get(7), 0);
+
+    program.input([5, 6, 9]);
+    assert_eq!(program.run_until_output(), Ok(Event::Output(5)));
+    assert_eq!(program.run_until_event(), Ok(Event::Halted));
+    assert_eq!(program.get(7), 6);
+    // Input that is never read stays queued.
+    assert_eq!(program.pending_input().collect::<Vec<_>>(), [&9]);
+
+    // A default input is consumed instead of waiting, but still reported.
+    let mut program = "3,7,4,7,3,7,99,0".parse::<Program<i64>>().unwrap();
+    program.set_default_input(-1);
+    assert_eq!(program.step(), Ok(Event::NeedsInput));
+    assert_eq!(program.pc(), 2);
+    assert_eq!(program.run_until_output(), Ok(Event::Output(-1)));
+    // Queued input still comes first.
+    program.input([4]);
+    assert_eq!(program.step(), Ok(Event::Stepped));
+    assert_eq!(program.get(7), 4);
+}
+
+/// Generates a program of valid and occasionally invalid instructions over a small memory, and
+/// input for it.
+fn random_program(rng: &mut StdRng) -> (Vec<i64>, Vec<i64>) {
+    const OPCODES: [i64; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+    let len = rng.gen_range(8..48);
+    let mut code = Vec::new();
+    while code.len() < len {
+        let op = OPCODES[rng.gen_range(0..OPCODES.len())];
+        let n = Opcode::try_from(op as u32).unwrap().num_params();
+        let writes = Opcode::try_from(op as u32).unwrap().write_param();
+        let mut word = op;
+        let mut params = Vec::new();
+        for p in 0..n {
+            let mode = match rng.gen_range(0..20) {
+                // Writing in immediate mode is a fault, so only do it rarely.
+                0 => 1,
+                _ if writes == Some(p) => 2 * rng.gen_range(0..2),
+                _ => rng.gen_range(0..3),
+            };
+            word += mode * 10i64.pow(p as u32 + 2);
+            params.push(match mode {
+                1 if matches!(op, 5 | 6) && p == 1 => rng.gen_range(0..len as i64),
+                1 => rng.gen_range(-20..20),
+                0 => rng.gen_range(0..64),
+                _ => rng.gen_range(-8..32),
+            });
+        }
+        code.push(word);
+        code.extend(params);
+    }
+    code.extend((code.len()..64).map(|_| rng.gen_range(-10..10)));
+    let input = (0..rng.gen_range(0..8))
+        .map(|_| rng.gen_range(-50..50))
+        .collect();
+    (code, input)
+}
+
+#[test]
+fn test_against_reference() {
+    let mut rng = StdRng::seed_from_u64(2019);
+    for _ in 0..500 {
+        let (code, input) = random_program(&mut rng);
+        run_lockstep(&code, &input, 1000);
+    }
+}
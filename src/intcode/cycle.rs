@@ -0,0 +1,91 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use num_traits::Zero;
+
+use super::memory::Memory;
+
+/// Remembers the states a program was in whenever it fell back on its default input. Reaching
+/// one of them again means it will repeat itself forever, as nothing from outside has changed
+/// in between.
+///
+/// Only addresses written since detection started are hashed, as the rest of memory is still
+/// what it was then. It is bookkeeping like the decode cache, so it is ignored when comparing
+/// programs.
+#[derive(Clone)]
+pub(super) struct CycleDetector<T> {
+    /// `Hash::hash` for the word type, which the rest of [`super::Program`] does not require.
+    hash_word: fn(&T, &mut DefaultHasher),
+    touched: BTreeSet<usize>,
+    seen: HashSet<u64>,
+}
+
+impl<T> CycleDetector<T>
+where
+    T: Hash,
+{
+    pub(super) fn new() -> Self {
+        Self {
+            hash_word: T::hash,
+            touched: BTreeSet::new(),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<T> CycleDetector<T>
+where
+    T: Zero + Clone,
+{
+    /// Notes a write to `addr`.
+    pub(super) fn touch(&mut self, addr: usize) {
+        self.touched.insert(addr);
+    }
+
+    /// Forgets the states seen so far, because input from outside may lead the program
+    /// somewhere else from the same state.
+    pub(super) fn forget(&mut self) {
+        self.seen.clear();
+    }
+
+    /// Starts over from the current state, as the program was restored to another one.
+    pub(super) fn restart(&mut self) {
+        self.touched.clear();
+        self.seen.clear();
+    }
+
+    /// Records the state of a program about to consume its default input. Returns `false` if
+    /// it has been in this state before.
+    pub(super) fn record(
+        &mut self,
+        pc: usize,
+        relative_base: isize,
+        default_input: &T,
+        memory: &Memory<T>,
+    ) -> bool {
+        let mut hasher = DefaultHasher::new();
+        (pc, relative_base).hash(&mut hasher);
+        (self.hash_word)(default_input, &mut hasher);
+        for &addr in &self.touched {
+            addr.hash(&mut hasher);
+            (self.hash_word)(&memory.get(addr), &mut hasher);
+        }
+        self.seen.insert(hasher.finish())
+    }
+}
+
+impl<T> PartialEq for CycleDetector<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for CycleDetector<T> {}
+
+impl<T> std::fmt::Debug for CycleDetector<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CycleDetector({} states seen)", self.seen.len())
+    }
+}
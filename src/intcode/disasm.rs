@@ -0,0 +1,340 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use arrayvec::ArrayVec;
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive};
+
+use super::{Instruction, Opcode, ParameterMode, Program};
+
+/// Number of words per `.data` line.
+const DATA_WIDTH: usize = 8;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Line<T> {
+    Code {
+        addr: usize,
+        inst: Instruction,
+        params: ArrayVec<T, 3>,
+    },
+    Data {
+        addr: usize,
+        words: Vec<T>,
+    },
+}
+
+impl<T> Line<T> {
+    pub fn addr(&self) -> usize {
+        match self {
+            Line::Code { addr, .. } | Line::Data { addr, .. } => *addr,
+        }
+    }
+
+    /// The number of memory words the line covers.
+    pub fn width(&self) -> usize {
+        match self {
+            Line::Code { params, .. } => params.len() + 1,
+            Line::Data { words, .. } => words.len(),
+        }
+    }
+}
+
+/// A listing of a program's initial memory, split into reachable instructions and data.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Disassembly<T> {
+    pub lines: Vec<Line<T>>,
+    /// Addresses that are the target of a jump.
+    pub labels: BTreeSet<usize>,
+}
+
+/// Disassembles the initial memory of `program`.
+///
+/// Code is found by following control flow from address 0. Jumps through memory cannot be
+/// followed statically, so immediate values moved into memory with `ADD x, #0` or `MUL x, #1`
+/// (the usual way to push a return address) are also tried as entry points. Every word that
+/// is not part of a reachable instruction is reported as data.
+pub fn disassemble<T>(program: &Program<T>) -> Disassembly<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    let code = (0..program.code_len())
+        .map(|addr| program.get(addr))
+        .collect::<Vec<_>>();
+    let mut starts = BTreeSet::new();
+    let mut covered = vec![false; code.len()];
+    let mut labels = BTreeSet::new();
+    let mut pointers = Vec::new();
+    let mut stack = vec![0];
+
+    loop {
+        while let Some(pc) = stack.pop() {
+            let Some((inst, params)) = decode_at(&code, pc) else {
+                continue;
+            };
+            let width = params.len() + 1;
+            if covered[pc..pc + width].iter().any(|&c| c) {
+                continue;
+            }
+            covered[pc..pc + width].fill(true);
+            starts.insert(pc);
+
+            let is_imm = |n: usize| inst.p_modes[n] == ParameterMode::Immediate;
+            match inst.code {
+                Opcode::Halt => {}
+                Opcode::Jnz | Opcode::Jz => {
+                    let cond = is_imm(0).then(|| params[0].is_zero() == (inst.code == Opcode::Jz));
+                    if cond != Some(false) && is_imm(1) {
+                        if let Some(target) = params[1].to_usize() {
+                            labels.insert(target);
+                            stack.push(target);
+                        }
+                    }
+                    if cond != Some(true) {
+                        stack.push(pc + width);
+                    }
+                }
+                Opcode::Add | Opcode::Mul => {
+                    if is_imm(0) && is_imm(1) {
+                        let identity = if inst.code == Opcode::Add {
+                            T::zero()
+                        } else {
+                            T::one()
+                        };
+                        let moved = if params[1] == identity {
+                            Some(params[0].clone())
+                        } else if params[0] == identity {
+                            Some(params[1].clone())
+                        } else {
+                            None
+                        };
+                        pointers.extend(moved.and_then(|p| p.to_usize()));
+                    }
+                    stack.push(pc + width);
+                }
+                _ => stack.push(pc + width),
+            }
+        }
+        let Some(pointer) = pointers.pop() else {
+            break;
+        };
+        if pointer < code.len() && !covered[pointer] && decode_at(&code, pointer).is_some() {
+            labels.insert(pointer);
+            stack.push(pointer);
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < code.len() {
+        if starts.contains(&addr) {
+            let (inst, params) = decode_at(&code, addr).unwrap();
+            let width = params.len() + 1;
+            lines.push(Line::Code { addr, inst, params });
+            addr += width;
+        } else {
+            let start = addr;
+            addr += 1;
+            while addr < code.len()
+                && !covered[addr]
+                && addr - start < DATA_WIDTH
+                && !labels.contains(&addr)
+            {
+                addr += 1;
+            }
+            lines.push(Line::Data {
+                addr: start,
+                words: code[start..addr].to_vec(),
+            });
+        }
+    }
+
+    // Targets that land outside the listing or inside an instruction get no label.
+    labels.retain(|&l| lines.iter().any(|line| line.addr() == l));
+
+    Disassembly { lines, labels }
+}
+
+fn decode_at<T>(code: &[T], pc: usize) -> Option<(Instruction, ArrayVec<T, 3>)>
+where
+    T: Clone + ToPrimitive,
+{
+    let inst = Instruction::try_from(code.get(pc)?.to_u32()?).ok()?;
+    let params = code.get(pc + 1..pc + 1 + inst.code.num_params())?;
+    Some((inst, params.iter().cloned().collect()))
+}
+
+/// Decodes the instruction at `pc` in the program's current memory.
+pub fn decode<T>(program: &Program<T>, pc: usize) -> Option<Line<T>>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    let inst = Instruction::try_from(program.get(pc).to_u32()?).ok()?;
+    let params = (1..=inst.code.num_params())
+        .map(|n| program.get(pc + n))
+        .collect();
+    Some(Line::Code {
+        addr: pc,
+        inst,
+        params,
+    })
+}
+
+fn operand<T>(inst: &Instruction, n: usize, val: &T, labels: &BTreeSet<usize>) -> String
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + Display,
+{
+    match inst.p_modes[n] {
+        ParameterMode::Position => format!("[{val}]"),
+        ParameterMode::Immediate => {
+            let is_target = matches!(inst.code, Opcode::Jnz | Opcode::Jz) && n == 1;
+            match val.to_usize() {
+                Some(target) if is_target && labels.contains(&target) => format!("#L{target}"),
+                _ => format!("#{val}"),
+            }
+        }
+        ParameterMode::Relative if *val < T::zero() => {
+            format!("[rb-{}]", T::zero() - val.clone())
+        }
+        ParameterMode::Relative => format!("[rb+{val}]"),
+    }
+}
+
+impl<T> Line<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + Display,
+{
+    /// The assembly form of the line, e.g. `JZ [101], #L0`. Jump targets found in `labels` are
+    /// referred to by label.
+    pub fn text(&self, labels: &BTreeSet<usize>) -> String {
+        match self {
+            Line::Code { inst, params, .. } => {
+                let write = inst.code.write_param();
+                let reads = params
+                    .iter()
+                    .enumerate()
+                    .filter(|(n, _)| Some(*n) != write)
+                    .map(|(n, p)| operand(inst, n, p, labels))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let mut text = inst.code.to_string();
+                if !reads.is_empty() {
+                    text = format!("{text} {reads}");
+                }
+                if let Some(n) = write {
+                    text = format!("{text} -> {}", operand(inst, n, &params[n], labels));
+                }
+                text
+            }
+            Line::Data { words, .. } => {
+                let words = words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+                format!(".data {}", words.join(", "))
+            }
+        }
+    }
+}
+
+/// Renders the address and raw words of the line, e.g. `0004: 1105,0,10`.
+impl<T> Display for Line<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let words = match self {
+            Line::Code { inst, params, .. } => std::iter::once(u32::from(*inst).to_string())
+                .chain(params.iter().map(|p| p.to_string()))
+                .collect::<Vec<_>>(),
+            Line::Data { words, .. } => words.iter().map(|w| w.to_string()).collect(),
+        };
+        write!(f, "{:04}: {}", self.addr(), words.join(","))
+    }
+}
+
+impl<T> Display for Disassembly<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            if self.labels.contains(&line.addr()) {
+                writeln!(f, "L{}:", line.addr())?;
+            }
+            let text = line.text(&self.labels);
+            writeln!(f, "    {text:<40} ; {line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(code: &str) -> Vec<String> {
+        let program = code.parse::<Program<i64>>().unwrap();
+        let disassembly = disassemble(&program);
+        disassembly
+            .lines
+            .iter()
+            .map(|line| line.text(&disassembly.labels))
+            .collect()
+    }
+
+    #[test]
+    fn test_modes() {
+        assert_eq!(
+            listing("109,-3,204,5,1001,100,1,100,21101,2,3,-1,99"),
+            [
+                "RELADJ #-3",
+                "OUTPUT [rb+5]",
+                "ADD [100], #1 -> [100]",
+                "ADD #2, #3 -> [rb-1]",
+                "HALT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99"
+            .parse::<Program<i64>>()
+            .unwrap();
+        assert_eq!(
+            disassemble(&program).to_string(),
+            "\
+L0:
+    RELADJ #1                                ; 0000: 109,1
+    OUTPUT [rb-1]                            ; 0002: 204,-1
+    ADD [100], #1 -> [100]                   ; 0004: 1001,100,1,100
+    EQ [100], #16 -> [101]                   ; 0008: 1008,100,16,101
+    JZ [101], #L0                            ; 0012: 1006,101,0
+    HALT                                     ; 0015: 99
+"
+        );
+    }
+
+    #[test]
+    fn test_data() {
+        // Jumps over a word, halts, and is followed by an invalid opcode and more data.
+        assert_eq!(
+            listing("1105,1,4,7,99,12345,-1,3"),
+            ["JNZ #1, #L4", ".data 7", "HALT", ".data 12345, -1, 3"]
+        );
+        // Data is split into lines of at most `DATA_WIDTH` words.
+        let lines = listing(&format!("99{}", ",0".repeat(DATA_WIDTH + 1)));
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2], ".data 0");
+    }
+
+    #[test]
+    fn test_pointer_entry() {
+        // Moves the address 8 into [20] and jumps through it.
+        assert_eq!(
+            listing("1101,8,0,20,106,0,20,99,104,1,99"),
+            [
+                "ADD #8, #0 -> [20]",
+                "JZ #0, [20]",
+                ".data 99",
+                "OUTPUT #1",
+                "HALT",
+            ]
+        );
+    }
+}
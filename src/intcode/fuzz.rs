@@ -0,0 +1,278 @@
+//! Differential fuzzing of the ways a [`Program`] can be run.
+//!
+//! [`check`] turns arbitrary bytes into a small program and its input, runs it with and without
+//! the decode cache, under a tracer, while being snapshotted and rewound, and again after a
+//! reset. It panics if the runs disagree on the outputs, how and after how many steps they
+//! ended, or the final state of the machine. The bytes come from `cargo fuzz` (see `fuzz/`) or,
+//! in the tests, from a seeded generator.
+
+use std::sync::{Arc, Mutex};
+
+use strum::IntoEnumIterator;
+
+use super::{cache::DecodeCache, trace::Profiler, Event, IntcodeErrorKind, Opcode, Program};
+
+/// Runs are cut off after this many steps, as random programs often loop forever.
+pub const MAX_STEPS: usize = 5000;
+
+/// Runs the program generated from `data` every way there is and panics if they disagree.
+pub fn check(data: &[u8]) {
+    check_case(&Case::from_bytes(data));
+}
+
+/// Reads bytes as small numbers, and zeros once they run out.
+struct Bytes<'a>(std::slice::Iter<'a, u8>);
+
+impl Bytes<'_> {
+    /// A number in `lo..hi`.
+    fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        lo + i64::from(self.0.next().copied().unwrap_or(0)) % (hi - lo)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Case {
+    code: Vec<i64>,
+    input: Vec<i64>,
+    default_input: Option<i64>,
+    /// How many steps the rewinding run runs ahead before restoring its snapshot.
+    rewind_every: usize,
+}
+
+impl Case {
+    /// Generates a program made of valid instructions, followed by some data. Addresses and
+    /// jump targets mostly stay near the code, so programs tend to loop and modify themselves.
+    fn from_bytes(data: &[u8]) -> Self {
+        let mut bytes = Bytes(data.iter());
+        let input = (0..bytes.range(0, 8))
+            .map(|_| bytes.range(-50, 50))
+            .collect();
+        let default_input = (bytes.range(0, 4) == 0).then(|| bytes.range(-50, 50));
+        let rewind_every = bytes.range(1, 16) as usize;
+
+        let opcodes = Opcode::iter().collect::<Vec<_>>();
+        let len = bytes.range(4, 64) as usize;
+        let mut code = Vec::with_capacity(64);
+        while code.len() < len {
+            let op = opcodes[bytes.range(0, opcodes.len() as i64) as usize];
+            let mut word = i64::from(u32::from(op));
+            let mut params = Vec::new();
+            for p in 0..op.num_params() {
+                let mode = match bytes.range(0, 20) {
+                    // Writing in immediate mode is a fault, so only do it rarely.
+                    0 => 1,
+                    n if op.write_param() == Some(p) => 2 * (n % 2),
+                    n => n % 3,
+                };
+                word += mode * 10i64.pow(p as u32 + 2);
+                params.push(match mode {
+                    1 if matches!(op, Opcode::Jnz | Opcode::Jz) && p == 1 => {
+                        bytes.range(0, len as i64)
+                    }
+                    1 => bytes.range(-20, 20),
+                    0 => bytes.range(0, 64),
+                    _ => bytes.range(-8, 32),
+                });
+            }
+            code.push(word);
+            code.extend(params);
+        }
+        while code.len() < 64 {
+            code.push(bytes.range(-10, 10));
+        }
+
+        Self {
+            code,
+            input,
+            default_input,
+            rewind_every,
+        }
+    }
+
+    /// A fresh machine with the input queued. Overflow checks are on, as random arithmetic
+    /// overflows easily and would otherwise panic.
+    fn program(&self) -> Program<i64> {
+        let mut program = Program::from(self.code.clone());
+        program.set_overflow_checks(true);
+        program.input(self.input.iter().copied());
+        if let Some(n) = self.default_input {
+            program.set_default_input(n);
+        }
+        program.cache();
+        program
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum End {
+    Halted,
+    Blocked,
+    Fault(IntcodeErrorKind),
+    OutOfSteps,
+}
+
+/// Everything runs are compared on.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Run {
+    outputs: Vec<i64>,
+    end: End,
+    /// Successful steps, including the one that halted or blocked.
+    steps: usize,
+    pc: usize,
+    relative_base: isize,
+    /// The non-zero words of memory.
+    memory: Vec<(usize, i64)>,
+}
+
+/// Steps `program` until it halts, blocks or faults, or [`MAX_STEPS`] run out. `before_step` may
+/// meddle with the machine before each step, given the number of steps so far, as long as it
+/// leaves it as it found it.
+fn run(program: &mut Program<i64>, mut before_step: impl FnMut(&mut Program<i64>, usize)) -> Run {
+    let mut outputs = Vec::new();
+    let mut steps = 0;
+    let end = loop {
+        if steps == MAX_STEPS {
+            break End::OutOfSteps;
+        }
+        before_step(program, steps);
+        let pc = program.pc();
+        match program.step() {
+            Err(e) => break End::Fault(e.kind),
+            Ok(event) => {
+                steps += 1;
+                match event {
+                    Event::Output(v) => outputs.push(v),
+                    Event::Halted => break End::Halted,
+                    // Input served by the default input moves on.
+                    Event::NeedsInput if program.pc() == pc => break End::Blocked,
+                    Event::NeedsInput | Event::Stepped => {}
+                    Event::BudgetExhausted => unreachable!("no budget is set"),
+                }
+            }
+        }
+    };
+    Run {
+        outputs,
+        end,
+        steps,
+        pc: program.pc(),
+        relative_base: program.relative_base(),
+        memory: program
+            .memory
+            .iter()
+            .filter(|&(_, &v)| v != 0)
+            .map(|(addr, &v)| (addr, v))
+            .collect(),
+    }
+}
+
+fn check_case(case: &Case) {
+    let expected = run(&mut case.program(), |_, _| {});
+
+    let mut uncached = case.program();
+    uncached.decoded = DecodeCache::default();
+    assert_eq!(
+        run(&mut uncached, |_, _| {}),
+        expected,
+        "without the decode cache: {case:?}"
+    );
+
+    let mut traced = case.program();
+    let profiler = Arc::new(Mutex::new(Profiler::default()));
+    traced.set_tracer(profiler.clone());
+    assert_eq!(
+        run(&mut traced, |_, _| {}),
+        expected,
+        "with a tracer: {case:?}"
+    );
+    assert_eq!(
+        profiler.lock().unwrap().total,
+        expected.steps as u64,
+        "traced steps: {case:?}"
+    );
+
+    let mut rewound = case.program();
+    let rewind = |program: &mut Program<i64>, steps: usize| {
+        if steps.is_multiple_of(case.rewind_every) {
+            let snapshot = program.snapshot();
+            for _ in 0..case.rewind_every {
+                if program.step().is_err() {
+                    break;
+                }
+            }
+            program.restore(&snapshot);
+        }
+    };
+    assert_eq!(
+        run(&mut rewound, rewind),
+        expected,
+        "rewinding every {} steps: {case:?}",
+        case.rewind_every
+    );
+
+    // The reset point shares memory and the decode cache with the first run.
+    let mut reset = case.program();
+    run(&mut reset, |_, _| {});
+    reset.reset();
+    assert_eq!(
+        run(&mut reset, |_, _| {}),
+        expected,
+        "after a reset: {case:?}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let case = Case::from_bytes(&[]);
+        assert_eq!(case.code.len(), 64);
+        assert_eq!(case.input, []);
+        assert_eq!(case.default_input, Some(-50));
+        assert_eq!(case.rewind_every, 1);
+
+        let data = (0..=255).collect::<Vec<u8>>();
+        assert_eq!(Case::from_bytes(&data), Case::from_bytes(&data));
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // Overwrites the output instruction, which was decoded when the program was loaded,
+        // with a halt.
+        let case = Case {
+            code: vec![1101, 1, 98, 4, 104, 7, 99],
+            input: Vec::new(),
+            default_input: None,
+            rewind_every: 1,
+        };
+        let expected = run(&mut case.program(), |_, _| {});
+        assert_eq!(
+            (expected.outputs.as_slice(), expected.end),
+            ([].as_slice(), End::Halted)
+        );
+        assert_eq!(expected.steps, 2);
+        check_case(&case);
+    }
+
+    #[test]
+    fn test_seeded() {
+        let mut rng = StdRng::seed_from_u64(2019);
+        let mut ends = Vec::new();
+        for _ in 0..1000 {
+            let data = (0..rng.gen_range(0..256))
+                .map(|_| rng.gen())
+                .collect::<Vec<u8>>();
+            ends.push(run(&mut Case::from_bytes(&data).program(), |_, _| {}).end);
+            check(&data);
+        }
+        // The generator should cover every way a run can end.
+        for end in [End::Halted, End::Blocked, End::OutOfSteps] {
+            assert!(ends.contains(&end), "no run ended with {end:?}");
+        }
+        assert!(ends.iter().any(|e| matches!(e, End::Fault(_))));
+    }
+}
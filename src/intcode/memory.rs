@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use num_traits::Zero;
+
+const PAGE_BITS: usize = 10;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+/// The default number of addressable words, 16Mi.
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+/// Paged Intcode memory.
+///
+/// Pages are allocated on first write and shared copy-on-write between clones, so forking a
+/// machine only copies the pages either side writes to afterwards. Unwritten memory reads as
+/// zero, so memories compare equal when they hold the same words, however they are paged.
+#[derive(Clone, Debug)]
+pub struct Memory<T> {
+    pages: Vec<Option<Arc<Vec<T>>>>,
+    limit: usize,
+    /// What unallocated pages hold, kept so that comparing memories only needs `PartialEq`.
+    zero: T,
+}
+
+impl<T> From<Vec<T>> for Memory<T>
+where
+    T: Zero + Clone,
+{
+    fn from(code: Vec<T>) -> Self {
+        let mut pages = Vec::with_capacity(code.len().div_ceil(PAGE_SIZE));
+        for chunk in code.chunks(PAGE_SIZE) {
+            let mut page = Vec::with_capacity(PAGE_SIZE);
+            page.extend_from_slice(chunk);
+            page.resize(PAGE_SIZE, T::zero());
+            pages.push(Some(Arc::new(page)));
+        }
+        Self {
+            pages,
+            limit: DEFAULT_MEMORY_LIMIT.max(code.len()),
+            zero: T::zero(),
+        }
+    }
+}
+
+impl<T> Memory<T>
+where
+    T: Zero + Clone,
+{
+    /// The number of addressable words.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    pub fn get(&self, addr: usize) -> T {
+        match self.pages.get(addr >> PAGE_BITS) {
+            Some(Some(page)) => page[addr & (PAGE_SIZE - 1)].clone(),
+            _ => T::zero(),
+        }
+    }
+
+    /// Writes `val` to `addr`.
+    ///
+    /// # Panics
+    ///
+    /// If `addr` is at or above the limit, rather than allocating pages up to any address.
+    pub fn set(&mut self, addr: usize, val: T) {
+        assert!(
+            addr < self.limit,
+            "address {addr} is past the memory limit of {} words",
+            self.limit
+        );
+        let index = addr >> PAGE_BITS;
+        if index >= self.pages.len() {
+            self.pages.resize(index + 1, None);
+        }
+        let page = self.pages[index].get_or_insert_with(|| Arc::new(vec![T::zero(); PAGE_SIZE]));
+        Arc::make_mut(page)[addr & (PAGE_SIZE - 1)] = val;
+    }
+
+    /// The address one past the last allocated page.
+    pub fn len(&self) -> usize {
+        self.pages.len() << PAGE_BITS
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Iterates over all allocated words with their addresses.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter_map(|(n, page)| Some((n << PAGE_BITS, page.as_ref()?)))
+            .flat_map(|(start, page)| page.iter().enumerate().map(move |(i, v)| (start + i, v)))
+    }
+}
+
+impl<T> PartialEq for Memory<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let zeros = |page: &Arc<Vec<T>>| page.iter().all(|w| *w == self.zero);
+        self.limit == other.limit
+            && (0..self.pages.len().max(other.pages.len())).all(|n| {
+                match (
+                    self.pages.get(n).cloned().flatten(),
+                    other.pages.get(n).cloned().flatten(),
+                ) {
+                    (Some(a), Some(b)) => a == b,
+                    (Some(page), None) | (None, Some(page)) => zeros(&page),
+                    (None, None) => true,
+                }
+            })
+    }
+}
+
+impl<T> Eq for Memory<T> where T: Eq {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eq() {
+        let memory = Memory::from(vec![1, 2, 3]);
+        let mut other = memory.clone();
+        other.set(5000, 0);
+        other.set(1, 2);
+        assert_eq!(memory, other);
+        other.set(5000, 4);
+        assert_ne!(memory, other);
+        other.set(5000, 0);
+        assert_eq!(memory, other);
+    }
+
+    #[test]
+    #[should_panic(expected = "past the memory limit")]
+    fn test_set_past_limit() {
+        let mut memory = Memory::from(vec![1, 2, 3]);
+        memory.set(usize::MAX / 2, 1);
+    }
+}
@@ -0,0 +1,961 @@
+use std::{collections::VecDeque, fmt::Display, hash::Hash, str::FromStr};
+
+use arrayvec::ArrayVec;
+use cache::DecodeCache;
+use cycle::CycleDetector;
+use memory::Memory;
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive, Zero};
+use strum::{Display as StrumDisplay, EnumIter, EnumString};
+use trace::{SharedTracer, TraceStep, TracerHook};
+
+pub mod analysis;
+pub mod ascii;
+pub mod asm;
+mod cache;
+#[cfg(test)]
+mod conformance;
+mod cycle;
+pub mod disasm;
+pub mod fuzz;
+pub mod memory;
+pub mod network;
+pub mod pipeline;
+pub mod save;
+#[cfg(feature = "async")]
+pub mod task;
+pub mod threaded;
+pub mod trace;
+
+/// An Intcode machine.
+///
+/// Memory is paged and copy-on-write, so cloning a program or taking a [`Snapshot`] is cheap and
+/// only the pages that are written to afterwards get copied.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Program<T> {
+    memory: Memory<T>,
+    decoded: DecodeCache,
+    /// The length of the loaded program.
+    code_len: usize,
+    pc: usize,
+    relative_base: isize,
+    input: VecDeque<T>,
+    reset_point: Snapshot<T>,
+    default_input: Option<T>,
+    tracer: TracerHook<T>,
+    overflow_checks: bool,
+    /// Steps left before [`Event::BudgetExhausted`].
+    budget: Option<usize>,
+    cycles: Option<CycleDetector<T>>,
+}
+
+/// The complete state of a [`Program`], taken with [`Program::snapshot`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Snapshot<T> {
+    memory: Memory<T>,
+    decoded: DecodeCache,
+    pc: usize,
+    relative_base: isize,
+    input: VecDeque<T>,
+    default_input: Option<T>,
+}
+
+impl<T> FromStr for Program<T>
+where
+    T: Num + Clone + ToPrimitive,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut code = Vec::with_capacity(s.split(',').count());
+        for num in s.trim().split(',') {
+            code.push(<T as Num>::from_str_radix(num, 10)?);
+        }
+        Ok(code.into())
+    }
+}
+
+impl<T> From<Vec<T>> for Program<T>
+where
+    T: Zero + Clone + ToPrimitive,
+{
+    fn from(code: Vec<T>) -> Self {
+        let code_len = code.len();
+        let reset_point = Snapshot {
+            decoded: DecodeCache::new(&code),
+            memory: code.into(),
+            pc: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            default_input: None,
+        };
+        Self {
+            memory: reset_point.memory.clone(),
+            decoded: reset_point.decoded.clone(),
+            code_len,
+            pc: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            reset_point,
+            default_input: None,
+            tracer: TracerHook::default(),
+            overflow_checks: false,
+            budget: None,
+            cycles: None,
+        }
+    }
+}
+
+/// Runs until the next output. Returns `None` once the program halts, faults, blocks on input or
+/// runs out of budget; call [`Program::step`] afterwards to find out which.
+impl<T> Iterator for Program<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NonBlockProgram<T> {
+    pub program: Program<T>,
+}
+
+impl<T> From<Program<T>> for NonBlockProgram<T> {
+    fn from(value: Program<T>) -> Self {
+        Self { program: value }
+    }
+}
+
+/// Yields every event except [`Event::Stepped`], ending once the program halts, faults or runs
+/// out of budget.
+impl<T> Iterator for NonBlockProgram<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    type Item = Event<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.program.run_until_event() {
+            Ok(Event::Halted | Event::BudgetExhausted) | Err(_) => None,
+            Ok(e) => Some(e),
+        }
+    }
+}
+
+impl<T> Program<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    /// Makes the current state the one [`Program::reset`] returns to.
+    pub fn cache(&mut self) {
+        self.reset_point = self.snapshot();
+    }
+
+    /// Returns to the state at the last call to [`Program::cache`], or to the freshly loaded
+    /// program if it was never called.
+    pub fn reset(&mut self) {
+        let reset_point = self.reset_point.clone();
+        self.restore(&reset_point);
+    }
+
+    pub fn snapshot(&self) -> Snapshot<T> {
+        Snapshot {
+            memory: self.memory.clone(),
+            decoded: self.decoded.clone(),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            input: self.input.clone(),
+            default_input: self.default_input.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot<T>) {
+        self.memory = snapshot.memory.clone();
+        self.decoded = snapshot.decoded.clone();
+        self.pc = snapshot.pc;
+        self.relative_base = snapshot.relative_base;
+        self.input = snapshot.input.clone();
+        self.default_input = snapshot.default_input.clone();
+        if let Some(cycles) = &mut self.cycles {
+            cycles.restart();
+        }
+    }
+
+    /// Makes additions and multiplications that do not fit in `T` fail with
+    /// [`IntcodeErrorKind::Overflow`] instead of wrapping or panicking. Word types without a
+    /// size limit such as `num::BigInt` never overflow.
+    pub fn set_overflow_checks(&mut self, enabled: bool) {
+        self.overflow_checks = enabled;
+    }
+
+    /// Makes [`Program::step`] return [`Event::BudgetExhausted`] without executing anything once
+    /// `steps` more steps have been taken. `None` lifts the limit.
+    pub fn set_budget(&mut self, steps: Option<usize>) {
+        self.budget = steps;
+    }
+
+    /// The steps left before the budget runs out, if there is one.
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    pub fn set_default_input(&mut self, n: T) {
+        self.default_input = Some(n);
+    }
+
+    pub fn input_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub fn input(&mut self, i: impl IntoIterator<Item = T>) {
+        self.input.extend(i);
+    }
+
+    pub fn pending_input(&self) -> impl Iterator<Item = &T> {
+        self.input.iter()
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: isize) {
+        self.relative_base = relative_base;
+    }
+
+    /// The number of words the program itself may address. Defaults to
+    /// [`memory::DEFAULT_MEMORY_LIMIT`].
+    pub fn memory_limit(&self) -> usize {
+        self.memory.limit()
+    }
+
+    /// Makes instructions that access memory at or above `limit` fail with
+    /// [`IntcodeErrorKind::OutOfMemory`].
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory.set_limit(limit);
+    }
+
+    /// The length of the loaded program.
+    pub fn code_len(&self) -> usize {
+        self.code_len
+    }
+
+    pub fn get(&self, index: usize) -> T {
+        self.memory.get(index)
+    }
+
+    /// Writes to memory.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is at or above [`Program::memory_limit`].
+    pub fn set(&mut self, index: usize, val: T) {
+        self.decoded.invalidate(index);
+        if let Some(cycles) = &mut self.cycles {
+            cycles.touch(index);
+        }
+        self.memory.set(index, val);
+    }
+
+    /// Runs until the program halts, blocks on input or runs out of budget, ignoring any output.
+    /// Returns [`Event::Halted`], [`Event::NeedsInput`] or [`Event::BudgetExhausted`].
+    pub fn execute(&mut self) -> Result<Event<T>, IntcodeError<T>> {
+        loop {
+            match self.run_until_output()? {
+                Event::Output(_) => {}
+                e => break Ok(e),
+            }
+        }
+    }
+
+    /// Runs until the next output, returning `Ok(None)` once the program halts, blocks on input
+    /// or runs out of budget.
+    pub fn try_next(&mut self) -> Result<Option<T>, IntcodeError<T>> {
+        match self.run_until_output()? {
+            Event::Output(v) => Ok(Some(v)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Runs until anything other than [`Event::Stepped`] happens.
+    pub fn run_until_event(&mut self) -> Result<Event<T>, IntcodeError<T>> {
+        loop {
+            match self.step()? {
+                Event::Stepped => {}
+                e => break Ok(e),
+            }
+        }
+    }
+
+    /// Runs until the program outputs a value, halts, or blocks on an empty input queue. Input
+    /// requests served by the default input do not stop it.
+    pub fn run_until_output(&mut self) -> Result<Event<T>, IntcodeError<T>> {
+        loop {
+            match self.run_until_event()? {
+                Event::NeedsInput if self.default_input.is_some() => {}
+                e => break Ok(e),
+            }
+        }
+    }
+
+    /// Runs until the program asks for input with an empty queue, halts or runs out of budget,
+    /// pushing every value output along the way into `out`. Returns [`Event::NeedsInput`],
+    /// [`Event::Halted`] or [`Event::BudgetExhausted`].
+    pub fn run_until_input(
+        &mut self,
+        out: &mut impl Extend<T>,
+    ) -> Result<Event<T>, IntcodeError<T>> {
+        loop {
+            match self.run_until_event()? {
+                Event::Output(v) => out.extend([v]),
+                e => break Ok(e),
+            }
+        }
+    }
+
+    /// Reports every instruction executed from now on to `tracer`. Clones of the program share
+    /// the same tracer.
+    pub fn set_tracer(&mut self, tracer: SharedTracer<T>) {
+        self.tracer = TracerHook(Some(tracer));
+    }
+
+    pub fn clear_tracer(&mut self) {
+        self.tracer = TracerHook(None);
+    }
+
+    /// Executes the instruction at the program counter.
+    ///
+    /// A failed step leaves the machine untouched, so stepping again reports the same error.
+    pub fn step(&mut self) -> Result<Event<T>, IntcodeError<T>> {
+        if self.budget == Some(0) {
+            return Ok(Event::BudgetExhausted);
+        }
+        let event = match &self.tracer.0 {
+            None => self.step_untraced(),
+            Some(tracer) => {
+                let tracer = tracer.clone();
+                self.step_traced(&tracer)
+            }
+        }?;
+        if let Some(budget) = &mut self.budget {
+            *budget -= 1;
+        }
+        Ok(event)
+    }
+
+    fn step_traced(&mut self, tracer: &SharedTracer<T>) -> Result<Event<T>, IntcodeError<T>> {
+        let pc = self.pc;
+        // Operands have to be resolved before the instruction overwrites them.
+        let decoded = self
+            .get(pc)
+            .to_u32()
+            .and_then(|w| Instruction::try_from(w).ok())
+            .map(|inst| {
+                let write = inst.code.write_param();
+                let operands = (0..inst.code.num_params())
+                    .filter(|&n| Some(n) != write)
+                    .filter_map(|n| self.get_with_pmode(n + 1, inst.p_modes[n]).ok())
+                    .collect::<ArrayVec<_, 3>>();
+                let write =
+                    write.and_then(|n| self.get_addr_with_pmode(n + 1, inst.p_modes[n]).ok());
+                (inst, operands, write)
+            });
+        let event = self.step_untraced()?;
+        if let (Some((inst, operands, write)), Ok(mut tracer)) = (decoded, tracer.lock()) {
+            tracer.record(&TraceStep {
+                pc,
+                inst,
+                operands,
+                // A blocked input instruction leaves the program counter where it was.
+                write: write.filter(|_| self.pc != pc),
+                event: event.clone(),
+            });
+        }
+        Ok(event)
+    }
+
+    fn step_untraced(&mut self) -> Result<Event<T>, IntcodeError<T>> {
+        let pc = self.pc;
+        match self.decoded.get(pc) {
+            Some(inst) => Ok(inst),
+            None => self
+                .get(pc)
+                .to_u32()
+                .ok_or(IntcodeErrorKind::InvalidOpcode)
+                .and_then(Instruction::try_from),
+        }
+        .and_then(|inst| self.process_inst(inst))
+        // A failed instruction has not written anything, so `pc` still holds it.
+        .map_err(|kind| IntcodeError {
+            pc,
+            raw: self.get(pc),
+            kind,
+        })
+    }
+
+    fn process_inst(&mut self, i: Instruction) -> Result<Event<T>, IntcodeErrorKind> {
+        match i.code {
+            Opcode::Add => {
+                let x = self.get_with_pmode(1, i.p_modes[0])?;
+                let y = self.get_with_pmode(2, i.p_modes[1])?;
+                let addr = self.get_write_addr(3, i.p_modes[2])?;
+                let sum = if self.overflow_checks {
+                    x.checked_add(&y).ok_or(IntcodeErrorKind::Overflow)?
+                } else {
+                    x + y
+                };
+                self.set(addr, sum);
+                self.pc += 4;
+                Ok(Event::Stepped)
+            }
+            Opcode::Mul => {
+                let x = self.get_with_pmode(1, i.p_modes[0])?;
+                let y = self.get_with_pmode(2, i.p_modes[1])?;
+                let addr = self.get_write_addr(3, i.p_modes[2])?;
+                let product = if self.overflow_checks {
+                    x.checked_mul(&y).ok_or(IntcodeErrorKind::Overflow)?
+                } else {
+                    x * y
+                };
+                self.set(addr, product);
+                self.pc += 4;
+                Ok(Event::Stepped)
+            }
+            Opcode::Input => {
+                let addr = self.get_write_addr(1, i.p_modes[0])?;
+                if let Some(inp) = self.input.pop_front() {
+                    if let Some(cycles) = &mut self.cycles {
+                        cycles.forget();
+                    }
+                    self.set(addr, inp);
+                    self.pc += 2;
+                    Ok(Event::Stepped)
+                } else if let Some(inp) = self.default_input.clone() {
+                    if let Some(cycles) = &mut self.cycles {
+                        if !cycles.record(self.pc, self.relative_base, &inp, &self.memory) {
+                            return Err(IntcodeErrorKind::InfiniteLoop);
+                        }
+                    }
+                    self.set(addr, inp);
+                    self.pc += 2;
+                    Ok(Event::NeedsInput)
+                } else {
+                    Ok(Event::NeedsInput)
+                }
+            }
+            Opcode::Output => {
+                let val = self.get_with_pmode(1, i.p_modes[0])?;
+                self.pc += 2;
+                Ok(Event::Output(val))
+            }
+            Opcode::Jnz => {
+                if !self.get_with_pmode(1, i.p_modes[0])?.is_zero() {
+                    self.pc = to_addr(self.get_with_pmode(2, i.p_modes[1])?)?;
+                } else {
+                    self.pc += 3;
+                }
+                Ok(Event::Stepped)
+            }
+            Opcode::Jz => {
+                if self.get_with_pmode(1, i.p_modes[0])?.is_zero() {
+                    self.pc = to_addr(self.get_with_pmode(2, i.p_modes[1])?)?;
+                } else {
+                    self.pc += 3;
+                }
+                Ok(Event::Stepped)
+            }
+            Opcode::Lt => {
+                let addr = self.get_write_addr(3, i.p_modes[2])?;
+                if self.get_with_pmode(1, i.p_modes[0])? < self.get_with_pmode(2, i.p_modes[1])? {
+                    self.set(addr, T::one());
+                } else {
+                    self.set(addr, T::zero());
+                }
+                self.pc += 4;
+                Ok(Event::Stepped)
+            }
+            Opcode::Eq => {
+                let addr = self.get_write_addr(3, i.p_modes[2])?;
+                if self.get_with_pmode(1, i.p_modes[0])? == self.get_with_pmode(2, i.p_modes[1])? {
+                    self.set(addr, T::one());
+                } else {
+                    self.set(addr, T::zero());
+                }
+                self.pc += 4;
+                Ok(Event::Stepped)
+            }
+            Opcode::Halt => Ok(Event::Halted),
+            Opcode::RelAdj => {
+                let adj = self
+                    .get_with_pmode(1, i.p_modes[0])?
+                    .to_isize()
+                    .ok_or(IntcodeErrorKind::Overflow)?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(adj)
+                    .ok_or(IntcodeErrorKind::Overflow)?;
+                self.pc += 2;
+                Ok(Event::Stepped)
+            }
+        }
+    }
+
+    /// Resolves the address referred to by the `param`th parameter of the current instruction.
+    fn get_addr_with_pmode(
+        &self,
+        param: usize,
+        pmode: ParameterMode,
+    ) -> Result<usize, IntcodeErrorKind> {
+        let index = self
+            .pc
+            .checked_add(param)
+            .ok_or(IntcodeErrorKind::Overflow)?;
+        let addr = match pmode {
+            ParameterMode::Position => to_addr(self.get(index))?,
+            ParameterMode::Immediate => index,
+            ParameterMode::Relative => {
+                let offset = self
+                    .get(index)
+                    .to_isize()
+                    .ok_or(IntcodeErrorKind::Overflow)?;
+                let addr = self
+                    .relative_base
+                    .checked_add(offset)
+                    .ok_or(IntcodeErrorKind::Overflow)?;
+                addr.try_into()
+                    .map_err(|_| IntcodeErrorKind::NegativeAddress)?
+            }
+        };
+        if addr >= self.memory.limit() {
+            return Err(IntcodeErrorKind::OutOfMemory);
+        }
+        Ok(addr)
+    }
+
+    fn get_write_addr(
+        &self,
+        param: usize,
+        pmode: ParameterMode,
+    ) -> Result<usize, IntcodeErrorKind> {
+        if pmode == ParameterMode::Immediate {
+            return Err(IntcodeErrorKind::ImmediateWrite);
+        }
+        self.get_addr_with_pmode(param, pmode)
+    }
+
+    fn get_with_pmode(&self, param: usize, pmode: ParameterMode) -> Result<T, IntcodeErrorKind> {
+        Ok(self.get(self.get_addr_with_pmode(param, pmode)?))
+    }
+}
+
+impl<T> Program<T>
+where
+    T: Num + Clone + Hash,
+{
+    /// Makes the program fail with [`IntcodeErrorKind::InfiniteLoop`] when it falls back on
+    /// its default input in a state it has done so in before, as it would then repeat itself
+    /// forever. Queued input resets what it has seen, and programs that never wait for input are
+    /// better stopped with [`Program::set_budget`].
+    pub fn set_loop_detection(&mut self, enabled: bool) {
+        self.cycles = enabled.then(CycleDetector::new);
+    }
+}
+
+fn to_addr<T>(val: T) -> Result<usize, IntcodeErrorKind>
+where
+    T: Num + ToPrimitive + PartialOrd,
+{
+    val.to_usize().ok_or(if val < T::zero() {
+        IntcodeErrorKind::NegativeAddress
+    } else {
+        IntcodeErrorKind::Overflow
+    })
+}
+
+/// What a single [`Program::step`] did.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event<T> {
+    /// An output instruction produced a value.
+    Output(T),
+    /// An input instruction found the input queue empty. If a default input is set it was
+    /// consumed, otherwise the program counter stays on the input instruction.
+    NeedsInput,
+    /// The program counter is on a halt instruction.
+    Halted,
+    /// Any other instruction was executed.
+    Stepped,
+    /// The budget set with [`Program::set_budget`] has run out, so nothing was executed.
+    BudgetExhausted,
+}
+
+/// A fault raised while executing the instruction at `pc`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IntcodeError<T> {
+    pub pc: usize,
+    /// The instruction word at `pc`.
+    pub raw: T,
+    pub kind: IntcodeErrorKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IntcodeErrorKind {
+    InvalidOpcode,
+    /// Holds the offending mode digit(s).
+    InvalidParameterMode(u32),
+    NegativeAddress,
+    ImmediateWrite,
+    Overflow,
+    /// An address at or above [`Program::memory_limit`] was accessed.
+    OutOfMemory,
+    /// The program waited for input in a state it had waited in before, see
+    /// [`Program::set_loop_detection`].
+    InfiniteLoop,
+}
+
+impl Display for IntcodeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntcodeErrorKind::InvalidOpcode => write!(f, "invalid opcode"),
+            IntcodeErrorKind::InvalidParameterMode(mode) => {
+                write!(f, "invalid parameter mode {mode}")
+            }
+            IntcodeErrorKind::NegativeAddress => write!(f, "negative address"),
+            IntcodeErrorKind::ImmediateWrite => write!(f, "write in immediate mode"),
+            IntcodeErrorKind::Overflow => write!(f, "arithmetic overflow"),
+            IntcodeErrorKind::OutOfMemory => write!(f, "out of memory"),
+            IntcodeErrorKind::InfiniteLoop => write!(f, "infinite loop"),
+        }
+    }
+}
+
+impl<T> Display for IntcodeError<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in instruction {} at pc {}",
+            self.kind, self.raw, self.pc
+        )
+    }
+}
+
+impl<T> std::error::Error for IntcodeError<T> where T: Display + std::fmt::Debug {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter)]
+pub enum ParameterMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl TryFrom<u32> for ParameterMode {
+    type Error = IntcodeErrorKind;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Position),
+            1 => Ok(Self::Immediate),
+            2 => Ok(Self::Relative),
+            _ => Err(IntcodeErrorKind::InvalidParameterMode(value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Instruction {
+    pub p_modes: [ParameterMode; 3],
+    pub code: Opcode,
+}
+
+impl TryFrom<u32> for Instruction {
+    type Error = IntcodeErrorKind;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        let code = Opcode::try_from(value % 100)?;
+        let mut p_modes = [ParameterMode::Position; 3];
+        let mut modes = value / 100;
+        for pmode in &mut p_modes {
+            *pmode = (modes % 10).try_into()?;
+            modes /= 10;
+        }
+        if modes != 0 {
+            return Err(IntcodeErrorKind::InvalidParameterMode(modes));
+        }
+        Ok(Self { p_modes, code })
+    }
+}
+
+impl From<ParameterMode> for u32 {
+    fn from(value: ParameterMode) -> Self {
+        match value {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
+        }
+    }
+}
+
+impl From<Instruction> for u32 {
+    fn from(value: Instruction) -> Self {
+        let modes = value
+            .p_modes
+            .iter()
+            .rev()
+            .fold(0, |acc, &pmode| acc * 10 + u32::from(pmode));
+        modes * 100 + u32::from(value.code)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, EnumIter, EnumString, StrumDisplay)]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum Opcode {
+    Add,
+    Mul,
+    Input,
+    Output,
+    Halt,
+    Jnz,
+    Jz,
+    Lt,
+    Eq,
+    RelAdj,
+}
+
+impl TryFrom<u32> for Opcode {
+    type Error = IntcodeErrorKind;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Add),
+            2 => Ok(Self::Mul),
+            3 => Ok(Self::Input),
+            4 => Ok(Self::Output),
+            5 => Ok(Self::Jnz),
+            6 => Ok(Self::Jz),
+            7 => Ok(Self::Lt),
+            8 => Ok(Self::Eq),
+            9 => Ok(Self::RelAdj),
+            99 => Ok(Self::Halt),
+            _ => Err(IntcodeErrorKind::InvalidOpcode),
+        }
+    }
+}
+
+impl From<Opcode> for u32 {
+    fn from(value: Opcode) -> Self {
+        match value {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::Jnz => 5,
+            Opcode::Jz => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::RelAdj => 9,
+            Opcode::Halt => 99,
+        }
+    }
+}
+
+impl Opcode {
+    pub fn num_params(self) -> usize {
+        match self {
+            Opcode::Halt => 0,
+            Opcode::Input | Opcode::Output | Opcode::RelAdj => 1,
+            Opcode::Jnz | Opcode::Jz => 2,
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+        }
+    }
+
+    /// The index of the parameter this opcode writes to, if any.
+    pub fn write_param(self) -> Option<usize> {
+        match self {
+            Opcode::Input => Some(0),
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => Some(2),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use super::*;
+
+    #[test]
+    fn test_snapshot_restore() {
+        // Adds every input to a running total in [100] and outputs the total.
+        let mut program = "3,50,1,50,100,100,4,100,1105,1,0"
+            .parse::<Program<i64>>()
+            .unwrap();
+        program.input([1, 2]);
+        assert_eq!(program.next(), Some(1));
+        let snapshot = program.snapshot();
+
+        assert_eq!(program.next(), Some(3));
+        program.input([10]);
+        assert_eq!(program.next(), Some(13));
+
+        let mut fork = program.clone();
+        fork.restore(&snapshot);
+        assert_eq!(fork.pending_input().copied().collect::<Vec<_>>(), [2]);
+        assert_eq!(fork.next(), Some(3));
+        assert_eq!(program.get(100), 13);
+        assert_eq!(fork.get(100), 3);
+    }
+
+    #[test]
+    fn test_reset_without_cache() {
+        let fresh = "3,50,1,50,100,100,4,100,1105,1,0"
+            .parse::<Program<i64>>()
+            .unwrap();
+        let mut program = fresh.clone();
+        program.set_default_input(5);
+        assert_eq!(program.next(), Some(5));
+        program.reset();
+        assert_eq!(program, fresh);
+
+        program.input([7]);
+        program.cache();
+        assert_eq!(program.next(), Some(7));
+        program.reset();
+        assert_eq!(program.next(), Some(7));
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        // Stores 7 at [rb+5000] and outputs it.
+        let mut program = "109,5000,21101,3,4,0,204,0,99"
+            .parse::<Program<i64>>()
+            .unwrap();
+        let mut limited = program.clone();
+        assert_eq!(program.next(), Some(7));
+        assert_eq!(program.get(5000), 7);
+
+        limited.set_memory_limit(4096);
+        assert_eq!(
+            limited.try_next(),
+            Err(IntcodeError {
+                pc: 2,
+                raw: 21101,
+                kind: IntcodeErrorKind::OutOfMemory
+            })
+        );
+        limited.set(4095, 1);
+        assert_eq!(limited.get(4095), 1);
+        limited.set_memory_limit(8192);
+        limited.set(5000, 1);
+        assert_eq!(limited.get(5000), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "past the memory limit")]
+    fn test_set_past_memory_limit() {
+        let mut program = "99".parse::<Program<i64>>().unwrap();
+        program.set_memory_limit(4096);
+        program.set(4096, 1);
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // Turns the halt at [4] into an output instruction.
+        let fresh = "1101,100,4,4,99,42,99".parse::<Program<i64>>().unwrap();
+        let mut program = fresh.clone();
+        assert_eq!(program.by_ref().collect::<Vec<_>>(), [42]);
+        program.reset();
+        assert_eq!(program.get(4), 99);
+        program.set(0, 99);
+        assert_eq!(program.next(), None);
+        assert_eq!(fresh.clone().next(), Some(42));
+    }
+
+    #[test]
+    fn test_overflow_checks() {
+        // Squares [18] five times, starting at 2^16, and outputs the result.
+        let code = "1101,0,5,19,2,18,18,18,1001,19,-1,19,1005,19,4,4,18,99,65536,0";
+        let mut program = code.parse::<Program<i64>>().unwrap();
+        program.set_overflow_checks(true);
+        assert_eq!(
+            program.try_next(),
+            Err(IntcodeError {
+                pc: 4,
+                raw: 2,
+                kind: IntcodeErrorKind::Overflow
+            })
+        );
+
+        let mut program = code.parse::<Program<BigInt>>().unwrap();
+        program.set_overflow_checks(true);
+        assert_eq!(program.next(), Some(BigInt::from(2).pow(512u32)));
+    }
+
+    #[test]
+    fn test_blocked_input() {
+        let mut program = "3,9,4,9,99".parse::<Program<i64>>().unwrap();
+        assert_eq!(program.next(), None);
+        assert_eq!(program.execute(), Ok(Event::NeedsInput));
+        program.input([5]);
+        assert_eq!(program.next(), Some(5));
+        assert_eq!(program.execute(), Ok(Event::Halted));
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut program = "1105,1,0".parse::<Program<i64>>().unwrap();
+        program.set_budget(Some(100));
+        assert_eq!(program.execute(), Ok(Event::BudgetExhausted));
+        assert_eq!(program.budget(), Some(0));
+        assert_eq!(program.next(), None);
+
+        program.set_budget(Some(1));
+        assert_eq!(program.step(), Ok(Event::Stepped));
+        assert_eq!(program.step(), Ok(Event::BudgetExhausted));
+        assert_eq!(program.pc(), 0);
+    }
+
+    #[test]
+    fn test_loop_detection() {
+        // Polls for input until it gets something other than -1, then outputs it.
+        let mut program = "3,20,1008,20,-1,21,1005,21,0,4,20,1105,1,0"
+            .parse::<Program<i64>>()
+            .unwrap();
+        program.set_default_input(-1);
+        program.set_loop_detection(true);
+        program.input([7]);
+        assert_eq!(program.try_next(), Ok(Some(7)));
+        assert_eq!(
+            program.try_next(),
+            Err(IntcodeError {
+                pc: 0,
+                raw: 3,
+                kind: IntcodeErrorKind::InfiniteLoop
+            })
+        );
+        program.input([8]);
+        assert_eq!(program.try_next(), Ok(Some(8)));
+
+        // Polls 50 times while counting in [30], so it never waits in the same state twice.
+        let mut program = "3,20,1001,30,1,30,1007,30,50,21,1005,21,0,4,30,99"
+            .parse::<Program<i64>>()
+            .unwrap();
+        program.set_default_input(-1);
+        program.set_loop_detection(true);
+        assert_eq!(program.next(), Some(50));
+    }
+}
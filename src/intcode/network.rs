@@ -0,0 +1,371 @@
+//! A network of Intcode machines exchanging packets, as in day 23.
+//!
+//! Every machine gets its address as its first input and reads `-1` whenever its input queue
+//! is empty. A packet is three consecutive outputs: the destination address, `x` and `y`.
+//!
+//! Machines are scheduled round-robin in address order, each running until it asks for input.
+//! Packets are delivered as soon as they are sent, so runs are fully deterministic.
+
+use std::{collections::VecDeque, fmt::Display};
+
+use num_traits::{CheckedAdd, CheckedMul, FromPrimitive, Num, ToPrimitive};
+
+use super::{IntcodeError, Program};
+
+/// The address of the NAT in day 23.
+pub const NAT_ADDRESS: usize = 255;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Packet<T> {
+    pub from: usize,
+    /// The destination address exactly as the sender output it.
+    pub to: T,
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Display for Packet<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}: ({}, {})", self.from, self.to, self.x, self.y)
+    }
+}
+
+/// Decides which machine receives a packet.
+pub trait Router<T> {
+    /// Returns the address of the receiving machine, or `None` to drop the packet.
+    fn route(&mut self, packet: &Packet<T>, machines: usize) -> Option<usize>;
+}
+
+impl<T, F> Router<T> for F
+where
+    F: FnMut(&Packet<T>, usize) -> Option<usize>,
+{
+    fn route(&mut self, packet: &Packet<T>, machines: usize) -> Option<usize> {
+        self(packet, machines)
+    }
+}
+
+/// Delivers packets to the machine they are addressed to and drops the rest.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DirectRouter;
+
+impl<T> Router<T> for DirectRouter
+where
+    T: ToPrimitive,
+{
+    fn route(&mut self, packet: &Packet<T>, machines: usize) -> Option<usize> {
+        packet.to.to_usize().filter(|&addr| addr < machines)
+    }
+}
+
+/// Receives the packets sent to its address and may send one whenever the network is idle.
+pub trait Nat<T> {
+    fn receive(&mut self, packet: Packet<T>);
+
+    /// Called when the network is idle. The `from` field of the returned packet is replaced by
+    /// the NAT's address.
+    fn wake(&mut self) -> Option<Packet<T>>;
+}
+
+/// The day 23 NAT: remembers the last packet it received and sends its `x` and `y` to machine
+/// 0 whenever the network is idle.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LastPacketNat<T> {
+    pub last: Option<Packet<T>>,
+}
+
+impl<T> Default for LastPacketNat<T> {
+    fn default() -> Self {
+        Self { last: None }
+    }
+}
+
+impl<T> Nat<T> for LastPacketNat<T>
+where
+    T: Num + Clone,
+{
+    fn receive(&mut self, packet: Packet<T>) {
+        self.last = Some(packet);
+    }
+
+    fn wake(&mut self) -> Option<Packet<T>> {
+        let last = self.last.as_ref()?;
+        Some(Packet {
+            from: 0,
+            to: T::zero(),
+            x: last.x.clone(),
+            y: last.y.clone(),
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NetEvent<T> {
+    /// A machine sent a packet. It has already been handed to the NAT, delivered or dropped.
+    Sent(Packet<T>),
+    /// The network was idle and the NAT sent a packet.
+    NatSent(Packet<T>),
+    /// The network was idle and there was nothing to wake it up.
+    Idle,
+}
+
+pub struct Network<T> {
+    machines: Vec<Program<T>>,
+    /// Outputs that do not make up a whole packet yet, per machine.
+    partial: Vec<Vec<T>>,
+    router: Box<dyn Router<T>>,
+    nat: Option<(usize, Box<dyn Nat<T>>)>,
+    idle_rounds: usize,
+    quiet_rounds: usize,
+    events: VecDeque<NetEvent<T>>,
+    log: Option<Vec<Packet<T>>>,
+}
+
+impl<T> Network<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    /// Boots `size` copies of `program` with addresses `0..size`, routing packets with
+    /// [`DirectRouter`] and without a NAT.
+    ///
+    /// # Panics
+    ///
+    /// If `T` cannot hold every address.
+    pub fn new(program: &Program<T>, size: usize) -> Self
+    where
+        T: FromPrimitive + 'static,
+    {
+        let machines = (0..size)
+            .map(|addr| {
+                let mut machine = program.clone();
+                machine.set_default_input(T::zero() - T::one());
+                machine
+                    .input([T::from_usize(addr).expect("network address does not fit in a word")]);
+                machine
+            })
+            .collect();
+        Self {
+            machines,
+            partial: vec![Vec::new(); size],
+            router: Box::new(DirectRouter),
+            nat: None,
+            idle_rounds: 1,
+            quiet_rounds: 0,
+            events: VecDeque::new(),
+            log: None,
+        }
+    }
+
+    pub fn set_router(&mut self, router: impl Router<T> + 'static) {
+        self.router = Box::new(router);
+    }
+
+    /// Hands every packet sent to `addr` to `nat` instead of routing it.
+    pub fn set_nat(&mut self, addr: usize, nat: impl Nat<T> + 'static) {
+        self.nat = Some((addr, Box::new(nat)));
+    }
+
+    /// Sets how many rounds in a row without any packets being sent make the network idle. The
+    /// default is 1.
+    pub fn set_idle_rounds(&mut self, rounds: usize) {
+        self.idle_rounds = rounds.max(1);
+    }
+
+    /// Starts keeping every packet sent, including those sent by the NAT, in
+    /// [`Network::packet_log`].
+    pub fn record_packets(&mut self) {
+        self.log.get_or_insert_with(Vec::new);
+    }
+
+    /// The packets sent since [`Network::record_packets`] was called, in order.
+    pub fn packet_log(&self) -> &[Packet<T>] {
+        self.log.as_deref().unwrap_or_default()
+    }
+
+    pub fn machines(&self) -> &[Program<T>] {
+        &self.machines
+    }
+
+    /// Runs the network until the next event.
+    pub fn next_event(&mut self) -> Result<NetEvent<T>, IntcodeError<T>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            self.run_round()?;
+        }
+    }
+
+    /// Runs every machine once until it asks for input.
+    fn run_round(&mut self) -> Result<(), IntcodeError<T>> {
+        let mut sent = false;
+        for from in 0..self.machines.len() {
+            self.machines[from].run_until_input(&mut self.partial[from])?;
+            while self.partial[from].len() >= 3 {
+                let mut words = self.partial[from].drain(..3);
+                let packet = Packet {
+                    from,
+                    to: words.next().unwrap(),
+                    x: words.next().unwrap(),
+                    y: words.next().unwrap(),
+                };
+                drop(words);
+                self.send(packet.clone());
+                self.events.push_back(NetEvent::Sent(packet));
+                sent = true;
+            }
+        }
+
+        self.quiet_rounds = if sent { 0 } else { self.quiet_rounds + 1 };
+        if self.quiet_rounds >= self.idle_rounds {
+            self.quiet_rounds = 0;
+            let woken = self.nat.as_mut().and_then(|(addr, nat)| {
+                let mut packet = nat.wake()?;
+                packet.from = *addr;
+                Some(packet)
+            });
+            match woken {
+                Some(packet) => {
+                    self.send(packet.clone());
+                    self.events.push_back(NetEvent::NatSent(packet));
+                }
+                None => self.events.push_back(NetEvent::Idle),
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, packet: Packet<T>) {
+        if let Some(log) = &mut self.log {
+            log.push(packet.clone());
+        }
+        if let Some((addr, nat)) = &mut self.nat {
+            if packet.to.to_usize() == Some(*addr) {
+                nat.receive(packet);
+                return;
+            }
+        }
+        if let Some(to) = self.router.route(&packet, self.machines.len()) {
+            if let Some(machine) = self.machines.get_mut(to) {
+                machine.input([packet.x, packet.y]);
+            }
+        }
+    }
+}
+
+/// Yields events until a machine faults.
+impl<T> Iterator for Network<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    type Item = NetEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Machine 0 starts a token that every machine passes on to the next one with `x`
+    /// incremented. The last machine sends it to the NAT.
+    const RING: &str = "
+            INPUT -> [addr]
+            JNZ [addr], #loop
+            OUTPUT #1
+            OUTPUT #0
+            OUTPUT #100
+    loop:   INPUT -> [x]
+            EQ [x], #-1 -> [c]
+            JNZ [c], #loop
+            INPUT -> [y]
+            ADD [addr], #1 -> [next]
+            ADD [x], #1 -> [x]
+            EQ [next], #3 -> [c]
+            JZ [c], #send
+            ADD #255, #0 -> [next]
+    send:   OUTPUT [next]
+            OUTPUT [x]
+            OUTPUT [y]
+            JNZ #1, #loop
+    addr:   .data 0
+    next:   .data 0
+    x:      .data 0
+    y:      .data 0
+    c:      .data 0
+    ";
+
+    fn packet(from: usize, to: i64, x: i64) -> Packet<i64> {
+        Packet {
+            from,
+            to,
+            x,
+            y: 100,
+        }
+    }
+
+    #[test]
+    fn test_ring() {
+        let program = assemble::<i64>(RING).unwrap();
+        let mut network = Network::new(&program, 3);
+        let events = network.by_ref().take(4).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                NetEvent::Sent(packet(0, 1, 0)),
+                NetEvent::Sent(packet(1, 2, 1)),
+                NetEvent::Sent(packet(2, 255, 2)),
+                NetEvent::Idle,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit in a word")]
+    fn test_address_overflow() {
+        Network::new(&Program::<i8>::from(vec![99]), 200);
+    }
+
+    #[test]
+    fn test_nat() {
+        let program = assemble::<i64>(RING).unwrap();
+        let mut network = Network::new(&program, 3);
+        network.set_nat(NAT_ADDRESS, LastPacketNat::default());
+        network.set_idle_rounds(2);
+        network.record_packets();
+        let nat_sent = network
+            .by_ref()
+            .filter_map(|e| match e {
+                NetEvent::NatSent(p) => Some(p),
+                _ => None,
+            })
+            .take(2)
+            .collect::<Vec<_>>();
+        assert_eq!(nat_sent, [packet(255, 0, 2), packet(255, 0, 5)]);
+        assert_eq!(network.packet_log().len(), 8);
+        assert_eq!(network.packet_log()[3].to_string(), "255 -> 0: (2, 100)");
+    }
+
+    #[test]
+    fn test_router() {
+        let program = assemble::<i64>(RING).unwrap();
+        let mut network = Network::new(&program, 3);
+        // Skips machine 1.
+        network.set_router(|p: &Packet<i64>, _| (p.to == 1).then_some(2));
+        let events = network.by_ref().take(3).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                NetEvent::Sent(packet(0, 1, 0)),
+                NetEvent::Sent(packet(2, 255, 1)),
+                NetEvent::Idle,
+            ]
+        );
+    }
+}
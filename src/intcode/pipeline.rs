@@ -0,0 +1,172 @@
+//! Programs wired output-to-input, such as the amplifier chains and feedback loops of day 7.
+//!
+//! Any graph works: a node's outputs are fed to all of its targets in the order the
+//! connections were made, and a node may feed itself. Programs with a default input never
+//! wait for input, so they do not belong in a pipeline.
+
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive};
+
+use super::{IntcodeError, Program};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Node<T> {
+    program: Program<T>,
+    outputs: Vec<T>,
+    targets: Vec<usize>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pipeline<T> {
+    nodes: Vec<Node<T>>,
+}
+
+impl<T> Default for Pipeline<T> {
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<T> Pipeline<T>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+{
+    /// Connects each program to the next one.
+    pub fn chain(programs: impl IntoIterator<Item = Program<T>>) -> Self {
+        let mut pipeline = Self::default();
+        for program in programs {
+            let node = pipeline.add(program);
+            if node > 0 {
+                pipeline.connect(node - 1, node);
+            }
+        }
+        pipeline
+    }
+
+    /// Connects each program to the next one and the last one back to the first.
+    pub fn ring(programs: impl IntoIterator<Item = Program<T>>) -> Self {
+        let mut pipeline = Self::chain(programs);
+        if let Some(last) = pipeline.nodes.len().checked_sub(1) {
+            pipeline.connect(last, 0);
+        }
+        pipeline
+    }
+
+    /// Adds a node running `program` and returns its id. Ids count up from 0.
+    pub fn add(&mut self, program: Program<T>) -> usize {
+        self.nodes.push(Node {
+            program,
+            outputs: Vec::new(),
+            targets: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Feeds everything `from` outputs from now on to `to`.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.nodes.len(), "no node {to}");
+        self.nodes[from].targets.push(to);
+    }
+
+    /// Queues input for a node.
+    pub fn input(&mut self, node: usize, i: impl IntoIterator<Item = T>) {
+        self.nodes[node].program.input(i);
+    }
+
+    /// Runs the nodes in id order, each until it halts or waits for input, and repeats until a
+    /// full pass produces no output. At that point every program has halted or is waiting for
+    /// input that no node will send.
+    pub fn run(&mut self) -> Result<(), IntcodeError<T>> {
+        let mut out = Vec::new();
+        loop {
+            let mut quiet = true;
+            for node in 0..self.nodes.len() {
+                self.nodes[node].program.run_until_input(&mut out)?;
+                if out.is_empty() {
+                    continue;
+                }
+                quiet = false;
+                for i in 0..self.nodes[node].targets.len() {
+                    let target = self.nodes[node].targets[i];
+                    self.nodes[target].program.input(out.iter().cloned());
+                }
+                self.nodes[node].outputs.append(&mut out);
+            }
+            if quiet {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Everything a node has output so far.
+    pub fn outputs(&self, node: usize) -> &[T] {
+        &self.nodes[node].outputs
+    }
+
+    pub fn program(&self, node: usize) -> &Program<T> {
+        &self.nodes[node].program
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Reads `inputs` values and outputs the result of folding them with `op`, starting at
+    /// `init`.
+    fn fold(op: &str, init: i64, inputs: i64) -> Program<i64> {
+        assemble(&format!(
+            "
+            ADD #{init}, #0 -> [acc]
+    loop:   INPUT -> [x]
+            {op} [acc], [x] -> [acc]
+            ADD [n], #-1 -> [n]
+            JNZ [n], #loop
+            OUTPUT [acc]
+            HALT
+    acc:    .data 0
+    x:      .data 0
+    n:      .data {inputs}
+            "
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dag() {
+        // Computes (2x + 1) + 3 * 2x.
+        let mut pipeline = Pipeline::default();
+        let double = pipeline.add(fold("MUL", 2, 1));
+        let inc = pipeline.add(fold("ADD", 1, 1));
+        let triple = pipeline.add(fold("MUL", 3, 1));
+        let sum = pipeline.add(fold("ADD", 0, 2));
+        pipeline.connect(double, inc);
+        pipeline.connect(double, triple);
+        pipeline.connect(inc, sum);
+        pipeline.connect(triple, sum);
+        pipeline.input(double, [5]);
+        pipeline.run().unwrap();
+        assert_eq!(pipeline.outputs(double), [10]);
+        assert_eq!(pipeline.outputs(sum), [41]);
+    }
+
+    #[test]
+    fn test_blocked() {
+        let mut pipeline = Pipeline::chain([fold("ADD", 0, 2), fold("ADD", 0, 1)]);
+        pipeline.input(0, [1]);
+        pipeline.run().unwrap();
+        assert!(pipeline.outputs(0).is_empty());
+        assert_eq!(pipeline.program(0).pc(), 4);
+        pipeline.input(0, [2]);
+        pipeline.run().unwrap();
+        assert_eq!(pipeline.outputs(1), [3]);
+    }
+}
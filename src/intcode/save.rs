@@ -0,0 +1,291 @@
+//! Save states: a running [`Program`] written to text, to be resumed later.
+//!
+//! ```text
+//! {
+//!   "format": "intcode-save-state",
+//!   "version": 1,
+//!   "pc": 4,
+//!   "relative_base": 0,
+//!   "input": ["7"],
+//!   "default_input": null,
+//!   "code": "3,100,4,100,1105,1,0",
+//!   "extra_mem": [[100,"5"]]
+//! }
+//! ```
+//!
+//! `code` is memory over the length of the loaded program, in the usual puzzle input format,
+//! and `extra_mem` lists the non-zero words past it in address order. Words are strings, so
+//! `num::BigInt` values and anything past the exact range of JSON numbers survive. Tracers,
+//! budgets, memory limits and overflow checks belong to whoever runs the program and are not
+//! saved.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Context};
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive, Zero};
+use tinyjson::JsonValue;
+
+use super::Program;
+
+const FORMAT: &str = "intcode-save-state";
+const VERSION: i64 = 1;
+
+/// The largest integer a JSON number holds exactly.
+const MAX_EXACT: f64 = (1u64 << 53) as f64;
+
+/// Writes the state of `program` as JSON.
+pub fn save<T>(program: &Program<T>) -> String
+where
+    T: Zero + Clone + std::fmt::Display,
+{
+    let word = |w: &T| JsonValue::String(w.to_string());
+    let code = (0..program.code_len)
+        .map(|addr| program.memory.get(addr).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let extra_mem = program
+        .memory
+        .iter()
+        .filter(|&(addr, w)| addr >= program.code_len && !w.is_zero())
+        .map(|(addr, w)| JsonValue::Array(vec![JsonValue::Number(addr as f64), word(w)]))
+        .collect();
+    let fields = [
+        ("format", JsonValue::String(FORMAT.to_string())),
+        ("version", JsonValue::Number(VERSION as f64)),
+        ("pc", JsonValue::Number(program.pc as f64)),
+        (
+            "relative_base",
+            JsonValue::Number(program.relative_base as f64),
+        ),
+        (
+            "input",
+            JsonValue::Array(program.input.iter().map(word).collect()),
+        ),
+        (
+            "default_input",
+            program.default_input.as_ref().map_or(JsonValue::Null, word),
+        ),
+        ("code", JsonValue::String(code)),
+        ("extra_mem", JsonValue::Array(extra_mem)),
+    ];
+    // Written by hand rather than as a `JsonValue::Object`, to keep the fields in order.
+    let fields = fields
+        .iter()
+        .map(|(name, value)| format!("  {name:?}: {}", value.stringify().unwrap()))
+        .collect::<Vec<_>>();
+    format!("{{\n{}\n}}\n", fields.join(",\n"))
+}
+
+/// Reads a program written by [`save`], checking that the state is complete and consistent.
+/// [`Program::reset`] on the result returns to the saved state.
+pub fn load<T>(text: &str) -> anyhow::Result<Program<T>>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    let json = text
+        .parse::<JsonValue>()
+        .context("save state is not valid JSON")?;
+    let fields = json
+        .get::<HashMap<String, JsonValue>>()
+        .context("save state is not a JSON object")?;
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .with_context(|| format!("save state has no `{name}`"))
+    };
+
+    ensure!(
+        field("format")?.get::<String>().map(String::as_str) == Some(FORMAT),
+        "not an Intcode save state"
+    );
+    let version = integer(field("version")?, "version")?;
+    ensure!(
+        version == VERSION,
+        "unsupported save state version {version}, expected {VERSION}"
+    );
+
+    let code = field("code")?
+        .get::<String>()
+        .context("`code` is not a string")?;
+    let code = match code.as_str() {
+        "" => Vec::new(),
+        code => code
+            .split(',')
+            .map(|w| word(w, "code"))
+            .collect::<anyhow::Result<_>>()?,
+    };
+    let mut program = Program::from(code);
+
+    let mut last = None;
+    for entry in array(field("extra_mem")?, "extra_mem")? {
+        let [addr, w] = array(entry, "extra_mem")?.as_slice() else {
+            bail!("`extra_mem` entries must be [address, word] pairs");
+        };
+        let addr = usize::try_from(integer(addr, "extra_mem")?)
+            .context("negative address in `extra_mem`")?;
+        ensure!(
+            addr >= program.code_len && addr < program.memory_limit(),
+            "`extra_mem` address {addr} is outside the memory past the code"
+        );
+        ensure!(
+            last.is_none_or(|last| addr > last),
+            "`extra_mem` is not in address order at {addr}"
+        );
+        last = Some(addr);
+        program.set(addr, word(string(w, "extra_mem")?, "extra_mem")?);
+    }
+
+    let pc = usize::try_from(integer(field("pc")?, "pc")?).context("negative `pc`")?;
+    ensure!(pc < program.memory_limit(), "`pc` {pc} is out of memory");
+    program.pc = pc;
+    program.relative_base = isize::try_from(integer(field("relative_base")?, "relative_base")?)
+        .context("`relative_base` is out of range")?;
+    for w in array(field("input")?, "input")? {
+        program.input.push_back(word(string(w, "input")?, "input")?);
+    }
+    match field("default_input")? {
+        JsonValue::Null => {}
+        w => program.default_input = Some(word(string(w, "default_input")?, "default_input")?),
+    }
+    program.cache();
+    Ok(program)
+}
+
+fn integer(value: &JsonValue, name: &str) -> anyhow::Result<i64> {
+    match value.get::<f64>() {
+        Some(&n) if n.fract() == 0.0 && n.abs() <= MAX_EXACT => Ok(n as i64),
+        _ => bail!("`{name}` is not an integer"),
+    }
+}
+
+fn array<'a>(value: &'a JsonValue, name: &str) -> anyhow::Result<&'a Vec<JsonValue>> {
+    value
+        .get::<Vec<JsonValue>>()
+        .with_context(|| format!("`{name}` is not an array"))
+}
+
+fn string<'a>(value: &'a JsonValue, name: &str) -> anyhow::Result<&'a str> {
+    value
+        .get::<String>()
+        .map(String::as_str)
+        .with_context(|| format!("`{name}` holds a word that is not a string"))
+}
+
+fn word<T>(s: &str, name: &str) -> anyhow::Result<T>
+where
+    T: Num,
+    <T as Num>::FromStrRadixErr: std::error::Error + Send + Sync + 'static,
+{
+    T::from_str_radix(s.trim(), 10).with_context(|| format!("invalid word {s:?} in `{name}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigInt;
+
+    use super::*;
+
+    /// Adds every input to a running total in [100] and outputs the total.
+    const TOTAL: &str = "3,50,1,50,100,100,4,100,1105,1,0";
+
+    #[test]
+    fn test_round_trip() {
+        let mut program = TOTAL.parse::<Program<i64>>().unwrap();
+        program.input([1, 2]);
+        assert_eq!(program.next(), Some(1));
+        program.set_default_input(-3);
+
+        let text = save(&program);
+        let mut loaded = load::<i64>(&text).unwrap();
+        assert_eq!(save(&loaded), text);
+        assert_eq!(loaded.pc(), program.pc());
+        assert_eq!(loaded.get(100), 1);
+        assert_eq!(loaded.pending_input().collect::<Vec<_>>(), [&2]);
+        let outputs = program.by_ref().take(3).collect::<Vec<_>>();
+        assert_eq!(outputs, [3, 0, -3]);
+        assert_eq!(loaded.by_ref().take(3).collect::<Vec<_>>(), outputs);
+
+        loaded.reset();
+        assert_eq!(loaded.next(), Some(3));
+    }
+
+    #[test]
+    fn test_format() {
+        let mut program = "3,100,4,100,1105,1,0".parse::<Program<i64>>().unwrap();
+        program.input([5, 7]);
+        assert_eq!(program.next(), Some(5));
+        assert_eq!(
+            save(&program),
+            r#"{
+  "format": "intcode-save-state",
+  "version": 1,
+  "pc": 4,
+  "relative_base": 0,
+  "input": ["7"],
+  "default_input": null,
+  "code": "3,100,4,100,1105,1,0",
+  "extra_mem": [[100,"5"]]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_big_numbers() {
+        let mut program = "104,1125899906842624,99"
+            .parse::<Program<BigInt>>()
+            .unwrap();
+        program.set(1000, BigInt::from(2).pow(100u32));
+        program.set_relative_base(-5);
+        let loaded = load::<BigInt>(&save(&program)).unwrap();
+        assert_eq!(loaded.get(1), BigInt::from(1u64 << 50));
+        assert_eq!(loaded.get(1000), BigInt::from(2).pow(100u32));
+        assert_eq!(loaded.relative_base(), -5);
+    }
+
+    #[test]
+    fn test_validation() {
+        let text = save(&TOTAL.parse::<Program<i64>>().unwrap());
+        let broken = |from: &str, to: &str| {
+            assert!(text.contains(from));
+            load::<i64>(&text.replace(from, to))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            broken("\"version\": 1", "\"version\": 2"),
+            "unsupported save state version 2, expected 1"
+        );
+        assert_eq!(
+            broken("intcode-save-state", "something-else"),
+            "not an Intcode save state"
+        );
+        assert_eq!(broken("\"pc\": 0", "\"pc\": 0.5"), "`pc` is not an integer");
+        assert_eq!(broken("\"pc\": 0", "\"pc\": -1"), "negative `pc`");
+        assert_eq!(
+            broken("\"input\": []", "\"input\": [1]"),
+            "`input` holds a word that is not a string"
+        );
+        assert_eq!(
+            broken("\"extra_mem\": []", "\"extra_mem\": [[3, \"1\"]]"),
+            "`extra_mem` address 3 is outside the memory past the code"
+        );
+        assert_eq!(
+            broken(
+                "\"extra_mem\": []",
+                "\"extra_mem\": [[30, \"1\"], [20, \"1\"]]"
+            ),
+            "`extra_mem` is not in address order at 20"
+        );
+        assert_eq!(
+            broken("1105,1,0", "1105,x,0"),
+            "invalid word \"x\" in `code`"
+        );
+        assert_eq!(
+            broken("  \"code\"", "  \"cod\""),
+            "save state has no `code`"
+        );
+        assert!(load::<i64>("").is_err());
+    }
+}
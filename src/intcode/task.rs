@@ -0,0 +1,358 @@
+//! Programs as async tasks, behind the `async` feature.
+//!
+//! [`ProgramStream`] turns a program and a stream of input into a stream of output, so machines
+//! can be wired together with [`channel`]s and driven by the small built-in [`Executor`] or
+//! [`block_on`] instead of an external runtime.
+//!
+//! ```no_run
+//! # use advent_of_code::intcode::{task::*, Program};
+//! let program = "3,9,8,9,10,9,4,9,99,-1,8".parse::<Program<i64>>().unwrap();
+//! let mut outputs = ProgramStream::new(program, iter([8]));
+//! assert_eq!(block_on(next(&mut outputs)), Some(1));
+//! ```
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive};
+
+use super::{Event, Program};
+
+/// An asynchronous sequence of values, with the same shape as `futures::Stream`.
+pub trait Stream {
+    type Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>;
+}
+
+/// Resolves to the next item of `stream`.
+pub fn next<S>(stream: &mut S) -> Next<'_, S>
+where
+    S: Stream + Unpin,
+{
+    Next(stream)
+}
+
+pub struct Next<'a, S>(&'a mut S);
+
+impl<S> Future for Next<'_, S>
+where
+    S: Stream + Unpin,
+{
+    type Output = Option<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.0).poll_next(cx)
+    }
+}
+
+/// A stream that is always ready with the items of an iterator.
+pub fn iter<I>(i: I) -> Iter<I::IntoIter>
+where
+    I: IntoIterator,
+{
+    Iter(i.into_iter())
+}
+
+pub struct Iter<I>(I);
+
+impl<I> Unpin for Iter<I> {}
+
+impl<I> Stream for Iter<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.next())
+    }
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    waker: Option<Waker>,
+}
+
+/// Creates an unbounded channel whose receiving end is a [`Stream`]. The stream ends once every
+/// sender is dropped and the queue is empty.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Mutex::new(Shared {
+        queue: VecDeque::new(),
+        senders: 1,
+        waker: None,
+    }));
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+#[derive(Debug)]
+pub struct Sender<T>(Arc<Mutex<Shared<T>>>);
+
+impl<T> Sender<T> {
+    pub fn send(&self, v: T) {
+        let mut shared = self.0.lock().unwrap();
+        shared.queue.push_back(v);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().unwrap().senders += 1;
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.0.lock().unwrap();
+        shared.senders -= 1;
+        if shared.senders == 0 {
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Receiver<T>(Arc<Mutex<Shared<T>>>);
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.0.lock().unwrap();
+        match shared.queue.pop_front() {
+            Some(v) => Poll::Ready(Some(v)),
+            None if shared.senders == 0 => Poll::Ready(None),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// How many instructions a [`ProgramStream`] runs in one poll before letting other tasks run.
+pub const STEPS_PER_POLL: usize = 10_000;
+
+/// The outputs of a program that reads its input from a stream.
+///
+/// The stream ends when the program halts or faults, or when it waits for input after the input
+/// stream has ended. Use [`ProgramStream::program`] to find out which. A program that runs
+/// [`STEPS_PER_POLL`] instructions without output or waiting for input, say one looping on its
+/// default input, yields and wakes itself, so it cannot starve other tasks.
+#[derive(Debug)]
+pub struct ProgramStream<T, S> {
+    program: Program<T>,
+    input: S,
+}
+
+// The program is never pinned.
+impl<T, S> Unpin for ProgramStream<T, S> where S: Unpin {}
+
+impl<T, S> ProgramStream<T, S> {
+    pub fn new(program: Program<T>, input: S) -> Self {
+        Self { program, input }
+    }
+
+    pub fn program(&self) -> &Program<T> {
+        &self.program
+    }
+
+    pub fn into_program(self) -> Program<T> {
+        self.program
+    }
+}
+
+impl<T, S> Stream for ProgramStream<T, S>
+where
+    T: Num + Clone + ToPrimitive + PartialOrd + CheckedAdd + CheckedMul + std::fmt::Debug,
+    S: Stream<Item = T> + Unpin,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        for _ in 0..STEPS_PER_POLL {
+            let pc = this.program.pc();
+            match this.program.step() {
+                Ok(Event::Output(v)) => return Poll::Ready(Some(v)),
+                Ok(Event::Halted | Event::BudgetExhausted) | Err(_) => return Poll::Ready(None),
+                Ok(Event::NeedsInput) if this.program.pc() == pc => {
+                    match Pin::new(&mut this.input).poll_next(cx) {
+                        Poll::Ready(Some(v)) => this.program.input([v]),
+                        Poll::Ready(None) => return Poll::Ready(None),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                Ok(_) => {}
+            }
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs `future` to completion on the current thread.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(v) => return v,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+struct FlagWaker(AtomicBool);
+
+impl Wake for FlagWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Task {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    woken: Arc<FlagWaker>,
+}
+
+/// A single-threaded executor that polls its tasks round-robin.
+#[derive(Default)]
+pub struct Executor {
+    tasks: Vec<Task>,
+}
+
+impl Executor {
+    pub fn spawn(&mut self, task: impl Future<Output = ()> + 'static) {
+        self.tasks.push(Task {
+            future: Box::pin(task),
+            woken: Arc::new(FlagWaker(AtomicBool::new(true))),
+        });
+    }
+
+    /// Runs until every task has finished or the remaining ones are all waiting on each other.
+    /// Returns the number of tasks left.
+    pub fn run(&mut self) -> usize {
+        while self.tasks.iter().any(|t| t.woken.0.load(Ordering::Relaxed)) {
+            self.tasks.retain_mut(|task| {
+                if !task.woken.0.swap(false, Ordering::Relaxed) {
+                    return true;
+                }
+                let waker = Waker::from(task.woken.clone());
+                task.future
+                    .as_mut()
+                    .poll(&mut Context::from_waker(&waker))
+                    .is_pending()
+            });
+        }
+        self.tasks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_block_on() {
+        let program = "3,9,8,9,10,9,4,9,99,-1,8".parse::<Program<i64>>().unwrap();
+        let mut outputs = ProgramStream::new(program, iter([7]));
+        assert_eq!(block_on(next(&mut outputs)), Some(0));
+        assert_eq!(block_on(next(&mut outputs)), None);
+        assert_eq!(outputs.program().pc(), 8);
+    }
+
+    #[test]
+    fn test_yields() {
+        // Polls for input forever, falling back on the default input.
+        let mut program = "3,100,1105,1,0".parse::<Program<i64>>().unwrap();
+        program.set_default_input(0);
+        let mut outputs = ProgramStream::new(program, iter([]));
+        let woken = Arc::new(FlagWaker(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let poll = Pin::new(&mut outputs).poll_next(&mut Context::from_waker(&waker));
+        assert_eq!(poll, Poll::Pending);
+        assert!(woken.0.load(Ordering::Relaxed));
+
+        // With a budget the loop ends after a few polls, but the other task finishes first.
+        let mut program = outputs.into_program();
+        program.set_budget(Some(3 * STEPS_PER_POLL));
+        let mut outputs = ProgramStream::new(program, iter([]));
+        let finished = Rc::new(RefCell::new(Vec::new()));
+        let mut executor = Executor::default();
+        let order = finished.clone();
+        executor.spawn(async move {
+            while next(&mut outputs).await.is_some() {}
+            order.borrow_mut().push("loop");
+        });
+        let order = finished.clone();
+        executor.spawn(async move { order.borrow_mut().push("other") });
+        assert_eq!(executor.run(), 0);
+        assert_eq!(*finished.borrow(), ["other", "loop"]);
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        let program = read_example(7, 3);
+        let result = Rc::new(RefCell::new(None));
+        let mut executor = Executor::default();
+
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..5).map(|_| channel()).unzip();
+        for (amp, (phase, input)) in [9, 8, 7, 6, 5].into_iter().zip(receivers).enumerate() {
+            senders[amp].send(phase);
+            let to_next = senders[(amp + 1) % 5].clone();
+            let mut outputs = ProgramStream::new(program.clone(), input);
+            let result = result.clone();
+            executor.spawn(async move {
+                while let Some(v) = next(&mut outputs).await {
+                    to_next.send(v);
+                    if amp == 4 {
+                        *result.borrow_mut() = Some(v);
+                    }
+                }
+            });
+        }
+        senders[0].send(0);
+        drop(senders);
+
+        assert_eq!(executor.run(), 0);
+        assert_eq!(*result.borrow(), Some(139629729));
+    }
+
+    fn read_example(day: u8, part: u8) -> Program<i64> {
+        crate::template::read_file_part("examples", crate::template::Day::new(day).unwrap(), part)
+            .parse()
+            .unwrap()
+    }
+}
@@ -0,0 +1,222 @@
+//! Running programs on other threads.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive};
+
+use super::{Event, IntcodeError, Program};
+
+/// How many instructions run between checks for a stop request.
+const STOP_CHECK_INTERVAL: usize = 4096;
+
+/// How long a program blocked on input waits for a value before checking for a stop request.
+const INPUT_POLL: Duration = Duration::from_millis(10);
+
+/// A program running on its own thread, taking input from and sending output to channels.
+///
+/// The thread ends when the program halts or faults, when [`ProgramThread::stop`] is called,
+/// or when the program waits for input after the input sender was dropped. Dropping the
+/// handle stops the thread and waits for it.
+pub struct ProgramThread<T> {
+    input: Option<Sender<T>>,
+    output: Receiver<T>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<Program<T>, IntcodeError<T>>>>,
+}
+
+impl<T> ProgramThread<T>
+where
+    T: Num
+        + Clone
+        + ToPrimitive
+        + PartialOrd
+        + CheckedAdd
+        + CheckedMul
+        + std::fmt::Debug
+        + Send
+        + Sync
+        + 'static,
+{
+    pub fn spawn(mut program: Program<T>) -> Self {
+        let (input, input_rx) = mpsc::channel();
+        let (output_tx, output) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_requested = stop.clone();
+        let handle = thread::spawn(move || {
+            let mut steps = 0;
+            loop {
+                steps += 1;
+                if steps % STOP_CHECK_INTERVAL == 0 && stop_requested.load(Ordering::Relaxed) {
+                    break;
+                }
+                let pc = program.pc();
+                match program.step()? {
+                    Event::Output(v) => {
+                        // Nobody is listening any more.
+                        if output_tx.send(v).is_err() {
+                            break;
+                        }
+                    }
+                    Event::Halted | Event::BudgetExhausted => break,
+                    Event::NeedsInput => {
+                        program.input(input_rx.try_iter());
+                        if program.pc() != pc || !program.input_empty() {
+                            continue;
+                        }
+                        match input_rx.recv_timeout(INPUT_POLL) {
+                            Ok(v) => program.input([v]),
+                            Err(RecvTimeoutError::Timeout) => {
+                                if stop_requested.load(Ordering::Relaxed) {
+                                    break;
+                                }
+                            }
+                            Err(RecvTimeoutError::Disconnected) => break,
+                        }
+                    }
+                    Event::Stepped => {}
+                }
+            }
+            Ok(program)
+        });
+        Self {
+            input: Some(input),
+            output,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Queues input for the program. Returns `false` if the thread has already ended.
+    pub fn send(&self, i: impl IntoIterator<Item = T>) -> bool {
+        let input = self.input.as_ref().unwrap();
+        i.into_iter().all(|v| input.send(v).is_ok())
+    }
+
+    /// A sender for the program's input, e.g. to hand to another thread.
+    pub fn sender(&self) -> Sender<T> {
+        self.input.clone().unwrap()
+    }
+
+    pub fn output(&self) -> &Receiver<T> {
+        &self.output
+    }
+
+    /// Asks the program to stop. It stops within a few thousand instructions, or soon after it
+    /// starts waiting for input.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Closes the input and waits for the thread to end, returning the program in the state it
+    /// stopped in.
+    pub fn join(mut self) -> Result<Program<T>, IntcodeError<T>> {
+        self.input = None;
+        match self.handle.take().unwrap().join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<T> Drop for ProgramThread<T> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.input = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Calls `f` with a clone of `program` for every job, spread over all available cores.
+/// Results are returned in the order of `jobs`.
+pub fn par_eval<T, J, R, F>(program: &Program<T>, jobs: impl IntoIterator<Item = J>, f: F) -> Vec<R>
+where
+    T: Clone + Send + Sync,
+    J: Send,
+    R: Send,
+    F: Fn(Program<T>, J) -> R + Sync,
+{
+    let jobs = jobs
+        .into_iter()
+        .map(|job| Mutex::new(Some(job)))
+        .collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut results = thread::scope(|s| {
+        let workers = (0..threads.min(jobs.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let n = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(n) else {
+                            break done;
+                        };
+                        let job = job.lock().unwrap().take().unwrap();
+                        done.push((n, f(program.clone(), job)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    results.sort_unstable_by_key(|&(n, _)| n);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outputs double each input until it reads 0.
+    const DOUBLER: &str = "3,15,1006,15,14,102,2,15,15,4,15,1105,1,0,99,0";
+
+    #[test]
+    fn test_program_thread() {
+        let program = DOUBLER.parse::<Program<i64>>().unwrap();
+        let thread = ProgramThread::spawn(program);
+        thread.send([1, 2]);
+        assert_eq!(thread.output().recv(), Ok(2));
+        assert_eq!(thread.output().recv(), Ok(4));
+        thread.send([21, 0]);
+        assert_eq!(thread.output().recv(), Ok(42));
+        let program = thread.join().unwrap();
+        assert_eq!(program.pc(), 14);
+    }
+
+    #[test]
+    fn test_stop() {
+        let program = DOUBLER.parse::<Program<i64>>().unwrap();
+        let thread = ProgramThread::spawn(program);
+        thread.stop();
+        assert_eq!(thread.join().unwrap().pc(), 0);
+
+        let program = "1105,1,0".parse::<Program<i64>>().unwrap();
+        let thread = ProgramThread::spawn(program);
+        thread.stop();
+        assert!(thread.join().is_ok());
+    }
+
+    #[test]
+    fn test_par_eval() {
+        let program = DOUBLER.parse::<Program<i64>>().unwrap();
+        let results = par_eval(&program, 1..=100, |mut p, n| {
+            p.input([n]);
+            p.next()
+        });
+        assert_eq!(results.len(), 100);
+        assert!(results.iter().zip(1..).all(|(&r, n)| r == Some(2 * n)));
+    }
+}
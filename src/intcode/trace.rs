@@ -0,0 +1,266 @@
+//! Opt-in instruction tracing and profiling.
+//!
+//! A tracer is attached with [`Program::set_tracer`] and sees every instruction the program
+//! executes. Programs without a tracer only pay for a single `is_some` check per step.
+//!
+//! ```no_run
+//! # use std::sync::{Arc, Mutex};
+//! # use advent_of_code::intcode::{trace::Profiler, Program};
+//! let mut program = "104,1,99".parse::<Program<i64>>().unwrap();
+//! let profiler = Arc::new(Mutex::new(Profiler::default()));
+//! program.set_tracer(profiler.clone());
+//! program.execute().unwrap();
+//! print!("{}", profiler.lock().unwrap().report(&program, 20));
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use arrayvec::ArrayVec;
+use num_traits::{CheckedAdd, CheckedMul, Num, ToPrimitive};
+
+use super::{disasm::decode, Event, Instruction, Opcode, Program};
+
+/// One executed instruction.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraceStep<T> {
+    pub pc: usize,
+    pub inst: Instruction,
+    /// The values of the parameters the instruction reads, in order.
+    pub operands: ArrayVec<T, 3>,
+    /// The address written to, if the instruction wrote to memory.
+    pub write: Option<usize>,
+    pub event: Event<T>,
+}
+
+impl<T> Display for TraceStep<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}: {}", self.pc, self.inst.code)?;
+        if !self.operands.is_empty() {
+            let operands = self.operands.iter().map(|o| o.to_string());
+            write!(f, " {}", operands.collect::<Vec<_>>().join(", "))?;
+        }
+        if let Some(addr) = self.write {
+            write!(f, " -> [{addr}]")?;
+        }
+        match &self.event {
+            Event::Output(v) => write!(f, " => output {v}"),
+            Event::NeedsInput => write!(f, " => needs input"),
+            Event::Halted => write!(f, " => halted"),
+            Event::BudgetExhausted => write!(f, " => out of budget"),
+            Event::Stepped => Ok(()),
+        }
+    }
+}
+
+pub trait Tracer<T> {
+    fn record(&mut self, step: &TraceStep<T>);
+}
+
+/// A tracer shared between a program, its clones and whoever reads the results.
+pub type SharedTracer<T> = Arc<Mutex<dyn Tracer<T> + Send>>;
+
+/// Holds a program's tracer. Tracers only observe a program, so they are ignored when comparing
+/// programs.
+#[derive(Clone)]
+pub(super) struct TracerHook<T>(pub(super) Option<SharedTracer<T>>);
+
+impl<T> Default for TracerHook<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T> PartialEq for TracerHook<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for TracerHook<T> {}
+
+impl<T> std::fmt::Debug for TracerHook<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Some(<tracer>)"),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+/// Keeps the last `capacity` executed instructions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraceBuffer<T> {
+    capacity: usize,
+    steps: VecDeque<TraceStep<T>>,
+}
+
+impl<T> TraceBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            steps: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &TraceStep<T>> {
+        self.steps.iter()
+    }
+}
+
+impl<T> Tracer<T> for TraceBuffer<T>
+where
+    T: Clone,
+{
+    fn record(&mut self, step: &TraceStep<T>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(step.clone());
+    }
+}
+
+impl<T> Display for TraceBuffer<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes one line per executed instruction, e.g. to a `BufWriter<File>`. Write errors are
+/// ignored so that tracing never stops the program.
+#[derive(Debug)]
+pub struct TraceLog<W> {
+    writer: W,
+}
+
+impl<W> TraceLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<T, W> Tracer<T> for TraceLog<W>
+where
+    T: Display,
+    W: Write,
+{
+    fn record(&mut self, step: &TraceStep<T>) {
+        let _ = writeln!(self.writer, "{step}");
+    }
+}
+
+/// Counts how often each address and each opcode is executed.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Profiler {
+    pub pc_hits: HashMap<usize, u64>,
+    pub opcode_hits: HashMap<Opcode, u64>,
+    pub total: u64,
+}
+
+impl<T> Tracer<T> for Profiler {
+    fn record(&mut self, step: &TraceStep<T>) {
+        *self.pc_hits.entry(step.pc).or_default() += 1;
+        *self.opcode_hits.entry(step.inst.code).or_default() += 1;
+        self.total += 1;
+    }
+}
+
+impl Profiler {
+    /// Renders the opcode counts and the `top` most executed addresses, disassembled from the
+    /// current memory of `program`.
+    pub fn report<T>(&self, program: &Program<T>, top: usize) -> String
+    where
+        T: Num
+            + Clone
+            + ToPrimitive
+            + PartialOrd
+            + CheckedAdd
+            + CheckedMul
+            + Display
+            + std::fmt::Debug,
+    {
+        let percent = |n: u64| 100.0 * n as f64 / self.total.max(1) as f64;
+        let mut out = format!("{} instructions executed\n\nBy opcode:\n", self.total);
+
+        let mut opcodes = self.opcode_hits.iter().collect::<Vec<_>>();
+        opcodes.sort_unstable_by_key(|&(op, &n)| (std::cmp::Reverse(n), u32::from(*op)));
+        for (op, &n) in opcodes {
+            out += &format!("  {:<8} {n:>12} {:>6.2}%\n", op.to_string(), percent(n));
+        }
+
+        out += "\nHot spots:\n";
+        let mut pcs = self.pc_hits.iter().collect::<Vec<_>>();
+        pcs.sort_unstable_by_key(|&(&pc, &n)| (std::cmp::Reverse(n), pc));
+        let labels = Default::default();
+        for (&pc, &n) in pcs.into_iter().take(top) {
+            let text = decode(program, pc).map_or_else(|| "?".to_string(), |l| l.text(&labels));
+            out += &format!("  {pc:04} {n:>12} {:>6.2}%  {text}\n", percent(n));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_buffer() {
+        let mut program = "3,9,8,9,10,9,4,9,99,-1,8".parse::<Program<i64>>().unwrap();
+        let buffer = Arc::new(Mutex::new(TraceBuffer::new(3)));
+        program.set_tracer(buffer.clone());
+        program.input([8]);
+        program.execute().unwrap();
+
+        let buffer = buffer.lock().unwrap();
+        let steps = buffer.steps().collect::<Vec<_>>();
+        assert_eq!(steps.len(), 3);
+        assert_eq!((steps[0].pc, steps[0].inst.code), (2, Opcode::Eq));
+        assert_eq!(steps[0].operands.as_slice(), [8, 8]);
+        assert_eq!(steps[0].write, Some(9));
+        assert_eq!(steps[1].event, Event::Output(1));
+        assert_eq!(steps[2].event, Event::Halted);
+        assert_eq!(
+            buffer.to_string().lines().next(),
+            Some("0002: EQ 8, 8 -> [9]")
+        );
+    }
+
+    #[test]
+    fn test_profiler() {
+        let mut program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99"
+            .parse::<Program<i64>>()
+            .unwrap();
+        let profiler = Arc::new(Mutex::new(Profiler::default()));
+        program.set_tracer(profiler.clone());
+        assert_eq!(program.by_ref().count(), 16);
+
+        let profiler = profiler.lock().unwrap();
+        assert_eq!(profiler.total, 16 * 5 + 1);
+        assert_eq!(profiler.pc_hits[&0], 16);
+        assert_eq!(profiler.opcode_hits[&Opcode::Halt], 1);
+        assert!(profiler
+            .report(&program, 1)
+            .contains("0000           16  19.75%  RELADJ #1"));
+    }
+}
@@ -1,4 +1,6 @@
-use advent_of_code::template::commands::{all, download, read, scaffold, solve, time};
+use advent_of_code::template::commands::{
+    all, debug, disasm, download, read, scaffold, solve, time,
+};
 use args::{parse, AppArguments};
 
 #[cfg(feature = "today")]
@@ -17,6 +19,13 @@ mod args {
         Read {
             day: Day,
         },
+        Disasm {
+            day: Day,
+            cfg: bool,
+        },
+        Debug {
+            day: Day,
+        },
         Scaffold {
             day: Day,
             download: bool,
@@ -26,6 +35,8 @@ mod args {
             release: bool,
             dhat: bool,
             submit: Option<u8>,
+            /// Flags for the solution itself, such as `--interactive`.
+            solution_args: Vec<String>,
         },
         All {
             release: bool,
@@ -62,6 +73,13 @@ mod args {
             Some("read") => AppArguments::Read {
                 day: args.free_from_str()?,
             },
+            Some("disasm") => AppArguments::Disasm {
+                day: args.free_from_str()?,
+                cfg: args.contains("--cfg"),
+            },
+            Some("debug") => AppArguments::Debug {
+                day: args.free_from_str()?,
+            },
             Some("scaffold") => AppArguments::Scaffold {
                 day: args.free_from_str()?,
                 download: args.contains("--download"),
@@ -71,6 +89,7 @@ mod args {
                 release: args.contains("--release"),
                 submit: args.opt_value_from_str("--submit")?,
                 dhat: args.contains("--dhat"),
+                solution_args: solution_args(&mut args)?,
             },
             #[cfg(feature = "today")]
             Some("today") => AppArguments::Today,
@@ -91,6 +110,29 @@ mod args {
 
         Ok(app_args)
     }
+
+    /// Flags that are passed on to the solution, and whether they take a value.
+    const SOLUTION_FLAGS: [(&str, bool); 5] = [
+        ("--interactive", false),
+        ("--replay", true),
+        ("--visual", false),
+        ("--record", true),
+        ("--playback", true),
+    ];
+
+    fn solution_args(args: &mut pico_args::Arguments) -> Result<Vec<String>, pico_args::Error> {
+        let mut solution_args = Vec::new();
+        for (flag, takes_value) in SOLUTION_FLAGS {
+            if !takes_value {
+                if args.contains(flag) {
+                    solution_args.push(flag.to_string());
+                }
+            } else if let Some(value) = args.opt_value_from_str::<_, String>(flag)? {
+                solution_args.extend([flag.to_string(), value]);
+            }
+        }
+        Ok(solution_args)
+    }
 }
 
 fn main() {
@@ -104,6 +146,8 @@ fn main() {
             AppArguments::Time { day, all, store } => time::handle(day, all, store),
             AppArguments::Download { day } => download::handle(day),
             AppArguments::Read { day } => read::handle(day),
+            AppArguments::Disasm { day, cfg } => disasm::handle(day, cfg),
+            AppArguments::Debug { day } => debug::handle(day),
             AppArguments::Scaffold { day, download } => {
                 scaffold::handle(day);
                 if download {
@@ -115,7 +159,8 @@ fn main() {
                 release,
                 dhat,
                 submit,
-            } => solve::handle(day, release, dhat, submit),
+                solution_args,
+            } => solve::handle(day, release, dhat, submit, solution_args),
             #[cfg(feature = "today")]
             AppArguments::Today => {
                 match Day::today() {
@@ -0,0 +1,433 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    intcode::{disasm::decode, Event, Program},
+    template::Day,
+};
+
+use super::disasm::load_program;
+
+const HELP: &str = "\
+s, step [n]          execute n instructions (default 1)
+c, continue          run until a breakpoint, watchpoint, halt or empty input queue
+b, break <pc>        toggle a breakpoint
+w, watch <addr>      toggle a watchpoint on a memory address
+x, mem <addr> [n]    show n words of memory (default 8)
+set <addr> <val>     write to memory
+pc [val]             show or set the program counter
+rb [val]             show or set the relative base
+in <val>...          queue input values
+ascii <text>         queue a line of text followed by a newline
+l, list [n]          disassemble n instructions from pc (default 5)
+i, info              show the machine state, breakpoints and watchpoints
+h, help              show this message
+q, quit              exit
+
+An empty line repeats the previous command.";
+
+/// The most memory words `x` shows at once.
+const MAX_WORDS: usize = 4096;
+
+/// A parsed command line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Command {
+    Nothing,
+    Step(usize),
+    Continue,
+    Break(usize),
+    Watch(usize),
+    Mem { start: usize, len: usize },
+    Set { addr: usize, val: i64 },
+    Pc(Option<usize>),
+    Rb(Option<isize>),
+    Input(Vec<i64>),
+    Ascii(String),
+    List(usize),
+    Info,
+    Help,
+    Quit,
+}
+
+impl Command {
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+        let arg = |n: usize| -> anyhow::Result<Option<i64>> {
+            args.get(n)
+                .map(|a| a.parse().map_err(|_| anyhow!("`{a}` is not a number")))
+                .transpose()
+        };
+        let addr = |n: usize| -> anyhow::Result<usize> {
+            let a = arg(n)?.ok_or_else(|| anyhow!("missing address"))?;
+            usize::try_from(a).map_err(|_| anyhow!("{a} is not a valid address"))
+        };
+        let count = |n: usize, default: usize| -> anyhow::Result<usize> {
+            match arg(n)? {
+                None => Ok(default),
+                Some(c) => usize::try_from(c).map_err(|_| anyhow!("{c} is not a valid count")),
+            }
+        };
+
+        Ok(match cmd {
+            "" => Command::Nothing,
+            "s" | "step" => Command::Step(count(0, 1)?),
+            "c" | "continue" => Command::Continue,
+            "b" | "break" => Command::Break(addr(0)?),
+            "w" | "watch" => Command::Watch(addr(0)?),
+            "x" | "mem" => {
+                let start = addr(0)?;
+                let len = count(1, 8)?;
+                if len > MAX_WORDS {
+                    bail!("can show at most {MAX_WORDS} words at once");
+                }
+                start.checked_add(len).ok_or_else(|| {
+                    anyhow!("{len} words from {start} run past the end of memory")
+                })?;
+                Command::Mem { start, len }
+            }
+            "set" => Command::Set {
+                addr: addr(0)?,
+                val: arg(1)?.ok_or_else(|| anyhow!("missing value"))?,
+            },
+            "pc" => Command::Pc(arg(0)?.map(|_| addr(0)).transpose()?),
+            "rb" => Command::Rb(
+                arg(0)?
+                    .map(|rb| isize::try_from(rb).map_err(|_| anyhow!("{rb} is out of range")))
+                    .transpose()?,
+            ),
+            "in" => Command::Input(
+                (0..args.len())
+                    .map(|n| arg(n).map(Option::unwrap))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            "ascii" => Command::Ascii(rest.to_string()),
+            "l" | "list" => Command::List(count(0, 5)?),
+            "i" | "info" => Command::Info,
+            "h" | "help" => Command::Help,
+            "q" | "quit" => Command::Quit,
+            _ => bail!("unknown command `{cmd}`, type `help` for a list of commands"),
+        })
+    }
+}
+
+struct Debugger<W> {
+    program: Program<i64>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    out: W,
+}
+
+pub fn handle(day: Day) {
+    let mut debugger = Debugger::new(load_program(day), io::stdout());
+    println!("Debugging day {day}. Type `help` for a list of commands.");
+    let _ = debugger.list(1);
+
+    let stdin = io::stdin();
+    let mut last = String::new();
+    loop {
+        print!("(icdb) ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        if !line.trim().is_empty() {
+            last = line.trim().to_string();
+        }
+        match Command::parse(&last).and_then(|cmd| debugger.execute(cmd)) {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(e) => println!("error: {e}"),
+        }
+    }
+}
+
+impl<W> Debugger<W>
+where
+    W: Write,
+{
+    fn new(program: Program<i64>, out: W) -> Self {
+        Self {
+            program,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            out,
+        }
+    }
+
+    /// Runs a single command, returning `true` when the session should end.
+    fn execute(&mut self, cmd: Command) -> anyhow::Result<bool> {
+        match cmd {
+            Command::Nothing => {}
+            Command::Step(n) => self.run(Some(n))?,
+            Command::Continue => self.run(None)?,
+            Command::Break(addr) => {
+                toggle(&mut self.out, &mut self.breakpoints, addr, "breakpoint")?
+            }
+            Command::Watch(addr) => {
+                toggle(&mut self.out, &mut self.watchpoints, addr, "watchpoint")?
+            }
+            Command::Mem { start, len } => {
+                let words = (start..start.saturating_add(len))
+                    .map(|a| self.program.get(a).to_string())
+                    .collect::<Vec<_>>();
+                writeln!(self.out, "{start:04}: {}", words.join(","))?;
+            }
+            Command::Set { addr, val } => {
+                let limit = self.program.memory_limit();
+                if addr >= limit {
+                    bail!("{addr} is past the memory limit of {limit} words");
+                }
+                self.program.set(addr, val)
+            }
+            Command::Pc(Some(pc)) => self.program.set_pc(pc),
+            Command::Pc(None) => writeln!(self.out, "pc = {}", self.program.pc())?,
+            Command::Rb(Some(rb)) => self.program.set_relative_base(rb),
+            Command::Rb(None) => writeln!(self.out, "rb = {}", self.program.relative_base())?,
+            Command::Input(vals) => self.program.input(vals),
+            Command::Ascii(text) => self
+                .program
+                .input(text.bytes().chain(*b"\n").map(i64::from)),
+            Command::List(n) => self.list(n)?,
+            Command::Info => self.info()?,
+            Command::Help => writeln!(self.out, "{HELP}")?,
+            Command::Quit => return Ok(true),
+        }
+        Ok(false)
+    }
+
+    /// Executes up to `limit` instructions, or until something interesting happens if `limit` is
+    /// `None`. Single steps print each instruction before executing it.
+    fn run(&mut self, limit: Option<usize>) -> io::Result<()> {
+        let mut steps = 0;
+        loop {
+            if limit.is_some_and(|l| steps == l) {
+                break;
+            }
+            let pc = self.program.pc();
+            if steps > 0 && self.breakpoints.contains(&pc) {
+                writeln!(self.out, "breakpoint at {pc}")?;
+                break;
+            }
+            if limit.is_some() {
+                self.list(1)?;
+            }
+            let watched = self
+                .watchpoints
+                .iter()
+                .map(|&a| (a, self.program.get(a)))
+                .collect::<Vec<_>>();
+
+            match self.program.step() {
+                Err(e) => {
+                    writeln!(self.out, "fault: {e}")?;
+                    break;
+                }
+                Ok(Event::Halted) => {
+                    writeln!(self.out, "halted at {pc}")?;
+                    break;
+                }
+                Ok(Event::NeedsInput) if self.program.pc() == pc => {
+                    writeln!(self.out, "waiting for input at {pc}")?;
+                    break;
+                }
+                Ok(Event::BudgetExhausted) => {
+                    writeln!(self.out, "step budget exhausted at {pc}")?;
+                    break;
+                }
+                Ok(Event::Output(v)) => print_output(&mut self.out, v)?,
+                Ok(Event::NeedsInput | Event::Stepped) => {}
+            }
+            steps += 1;
+
+            let mut hit = false;
+            for (addr, old) in watched {
+                let new = self.program.get(addr);
+                if new != old {
+                    writeln!(self.out, "watchpoint [{addr}]: {old} -> {new}")?;
+                    hit = true;
+                }
+            }
+            if hit {
+                break;
+            }
+        }
+        self.out.flush()
+    }
+
+    fn list(&mut self, n: usize) -> io::Result<()> {
+        let no_labels = BTreeSet::new();
+        let mut pc = self.program.pc();
+        for _ in 0..n {
+            match decode(&self.program, pc) {
+                Some(line) => {
+                    let marker = if self.breakpoints.contains(&pc) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    writeln!(self.out, "{marker} {:<40} ; {line}", line.text(&no_labels))?;
+                    pc += line.width();
+                }
+                None => {
+                    writeln!(
+                        self.out,
+                        "  {pc:04}: {} (not an instruction)",
+                        self.program.get(pc)
+                    )?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn info(&mut self) -> io::Result<()> {
+        let input = self
+            .program
+            .pending_input()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        writeln!(self.out, "pc = {}", self.program.pc())?;
+        writeln!(self.out, "rb = {}", self.program.relative_base())?;
+        writeln!(self.out, "input = [{}]", input.join(","))?;
+        writeln!(self.out, "breakpoints = {:?}", self.breakpoints)?;
+        writeln!(self.out, "watchpoints = {:?}", self.watchpoints)
+    }
+}
+
+fn toggle(
+    out: &mut impl Write,
+    set: &mut BTreeSet<usize>,
+    addr: usize,
+    name: &str,
+) -> io::Result<()> {
+    if set.remove(&addr) {
+        writeln!(out, "removed {name} at {addr}")
+    } else {
+        set.insert(addr);
+        writeln!(out, "added {name} at {addr}")
+    }
+}
+
+fn print_output(out: &mut impl Write, v: i64) -> io::Result<()> {
+    match u8::try_from(v) {
+        Ok(c) if c.is_ascii() => write!(out, "{}", c as char)?,
+        _ => writeln!(out, "<{v}>")?,
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds 1000 to [100] and outputs it, forever.
+    const COUNTER: &str = "1001,100,1000,100,4,100,1105,1,0";
+
+    fn debugger() -> Debugger<Vec<u8>> {
+        Debugger::new(COUNTER.parse().unwrap(), Vec::new())
+    }
+
+    /// Runs `line` and returns what it printed.
+    fn run(debugger: &mut Debugger<Vec<u8>>, line: &str) -> String {
+        debugger.out.clear();
+        debugger.execute(Command::parse(line).unwrap()).unwrap();
+        String::from_utf8(debugger.out.clone()).unwrap()
+    }
+
+    fn parse_error(line: &str) -> String {
+        Command::parse(line).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Command::parse("").unwrap(), Command::Nothing);
+        assert_eq!(Command::parse("s").unwrap(), Command::Step(1));
+        assert_eq!(Command::parse("step 3").unwrap(), Command::Step(3));
+        assert_eq!(Command::parse("b 4").unwrap(), Command::Break(4));
+        assert_eq!(Command::parse("watch 100").unwrap(), Command::Watch(100));
+        assert_eq!(
+            Command::parse("x 100").unwrap(),
+            Command::Mem { start: 100, len: 8 }
+        );
+        assert_eq!(
+            Command::parse("set 100 -5").unwrap(),
+            Command::Set { addr: 100, val: -5 }
+        );
+        assert_eq!(
+            Command::parse("in 1 -2").unwrap(),
+            Command::Input(vec![1, -2])
+        );
+
+        assert_eq!(parse_error("step -1"), "-1 is not a valid count");
+        assert_eq!(parse_error("b -4"), "-4 is not a valid address");
+        assert_eq!(parse_error("w"), "missing address");
+        assert_eq!(
+            parse_error("x 0 99999999999"),
+            "can show at most 4096 words at once"
+        );
+        assert_eq!(parse_error("x foo"), "`foo` is not a number");
+        assert_eq!(parse_error("set 100"), "missing value");
+        assert_eq!(
+            parse_error("frob"),
+            "unknown command `frob`, type `help` for a list of commands"
+        );
+    }
+
+    #[test]
+    fn test_step() {
+        let mut debugger = debugger();
+        let out = run(&mut debugger, "s 2");
+        assert!(out.contains("ADD [100], #1000 -> [100]"), "{out}");
+        assert!(out.ends_with("<1000>\n"), "{out}");
+        assert_eq!(debugger.program.pc(), 6);
+    }
+
+    #[test]
+    fn test_break() {
+        let mut debugger = debugger();
+        assert_eq!(run(&mut debugger, "b 4"), "added breakpoint at 4\n");
+        assert_eq!(run(&mut debugger, "c"), "breakpoint at 4\n");
+        assert_eq!(run(&mut debugger, "c"), "<1000>\nbreakpoint at 4\n");
+        assert_eq!(run(&mut debugger, "b 4"), "removed breakpoint at 4\n");
+    }
+
+    #[test]
+    fn test_watch() {
+        let mut debugger = debugger();
+        run(&mut debugger, "w 100");
+        assert_eq!(run(&mut debugger, "c"), "watchpoint [100]: 0 -> 1000\n");
+        assert_eq!(
+            run(&mut debugger, "c"),
+            "<1000>\nwatchpoint [100]: 1000 -> 2000\n"
+        );
+    }
+
+    #[test]
+    fn test_mem_and_set() {
+        let mut debugger = debugger();
+        assert_eq!(run(&mut debugger, "x 0 4"), "0000: 1001,100,1000,100\n");
+        run(&mut debugger, "set 100 7");
+        run(&mut debugger, "set 101 8");
+        assert_eq!(run(&mut debugger, "x 100 3"), "0100: 7,8,0\n");
+        run(&mut debugger, "s");
+        assert_eq!(debugger.program.get(100), 1007);
+
+        let cmd = Command::parse("set 99999999999 1").unwrap();
+        assert_eq!(
+            debugger.execute(cmd).unwrap_err().to_string(),
+            "99999999999 is past the memory limit of 16777216 words"
+        );
+        let cmd = Command::Mem {
+            start: usize::MAX - 1,
+            len: 8,
+        };
+        assert!(!debugger.execute(cmd).unwrap());
+    }
+}
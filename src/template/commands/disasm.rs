@@ -0,0 +1,36 @@
+use std::{fs, process};
+
+use crate::{
+    intcode::{analysis::Cfg, disasm::disassemble, Program},
+    template::Day,
+};
+
+/// Loads the day's input as an Intcode program, exiting with a message if that fails.
+pub fn load_program(day: Day) -> Program<i64> {
+    let input_path = format!("data/inputs/{day}.txt");
+
+    let input = match fs::read_to_string(&input_path) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Failed to read input file \"{input_path}\": {e}");
+            process::exit(1);
+        }
+    };
+
+    match input.parse::<Program<i64>>() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("\"{input_path}\" is not an Intcode program: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+pub fn handle(day: Day, cfg: bool) {
+    let program = load_program(day);
+    if cfg {
+        print!("{}", Cfg::build(&program).to_dot());
+    } else {
+        print!("{}", disassemble(&program));
+    }
+}
@@ -1,4 +1,6 @@
 pub mod all;
+pub mod debug;
+pub mod disasm;
 pub mod download;
 pub mod read;
 pub mod scaffold;
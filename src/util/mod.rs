@@ -1,4 +1,5 @@
 pub mod digits;
 pub mod euclid;
 pub mod graph;
+pub mod ocr;
 pub mod point;
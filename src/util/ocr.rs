@@ -0,0 +1,226 @@
+//! Reading the block letters that puzzles draw as their answer.
+//!
+//! Two fonts are known: the small one, 4 pixels wide and 6 high with a 1 pixel gap, and the
+//! large one, 6 wide and 10 high with a 2 pixel gap. The font is picked by the height of the
+//! text.
+
+use std::collections::HashMap;
+
+use super::point::Pt;
+
+struct Font {
+    height: usize,
+    /// Distance from the start of one letter to the start of the next.
+    pitch: usize,
+    glyphs: &'static [(char, &'static [&'static str])],
+}
+
+const SMALL: Font = Font {
+    height: 6,
+    pitch: 5,
+    glyphs: &[
+        ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+        ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+        ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+        ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+        ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+        ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+        ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+        ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+        ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+        ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+        ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+        ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+        ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+        ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+        ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+        // The only letter that uses the gap.
+        ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+        ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+    ],
+};
+
+#[rustfmt::skip]
+const LARGE: Font = Font {
+    height: 10,
+    pitch: 8,
+    glyphs: &[
+        ('A', &["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+        ('B', &["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+        ('C', &[".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+        ('E', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+        ('F', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+        ('G', &[".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+        ('H', &["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+        ('J', &["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+        ('K', &["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+        ('L', &["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+        ('N', &["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+        ('P', &["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+        ('R', &["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+        ('X', &["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+        ('Z', &["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+    ],
+};
+
+impl Font {
+    /// Reads the letter whose leftmost column is `left`. Columns outside the bitmap are unlit.
+    fn letter(&self, rows: &[&[bool]], left: isize) -> Option<char> {
+        let lit = |y: usize, x: isize| {
+            usize::try_from(left + x).is_ok_and(|x| rows[y].get(x).copied().unwrap_or(false))
+        };
+        self.glyphs
+            .iter()
+            .find(|(_, glyph)| {
+                glyph.iter().enumerate().all(|(y, row)| {
+                    (0..self.pitch)
+                        .all(|x| lit(y, x as isize) == (row.as_bytes().get(x) == Some(&b'#')))
+                })
+            })
+            .map(|&(c, _)| c)
+    }
+
+    /// Reads a line of text that starts at `left`.
+    fn text(&self, rows: &[&[bool]], left: isize, width: usize) -> Option<String> {
+        (left..width as isize)
+            .step_by(self.pitch)
+            .map(|x| self.letter(rows, x))
+            .collect()
+    }
+}
+
+/// Reads text from rows of pixels, where `true` is lit. Unlit rows and columns around the text
+/// are ignored. Returns `None` if the text is not made of known letters.
+pub fn read<R>(rows: &[R]) -> Option<String>
+where
+    R: AsRef<[bool]>,
+{
+    let lit_row = |row: &R| row.as_ref().contains(&true);
+    let top = rows.iter().position(lit_row)?;
+    let bottom = rows.iter().rposition(lit_row)?;
+    let rows = rows[top..=bottom]
+        .iter()
+        .map(|r| r.as_ref())
+        .collect::<Vec<_>>();
+    let lit_column = |x: usize| rows.iter().any(|r| r.get(x) == Some(&true));
+    let width = rows.iter().map(|r| r.len()).max()?;
+    let first = (0..width).find(|&x| lit_column(x))?;
+    let width = (0..width).rfind(|&x| lit_column(x))? + 1;
+
+    let font = [SMALL, LARGE]
+        .into_iter()
+        .find(|f| f.height == rows.len())?;
+    // A letter may start with an unlit column, so the text can start a little left of the
+    // first lit pixel.
+    (0..font.pitch as isize).find_map(|shift| font.text(&rows, first as isize - shift, width))
+}
+
+/// Reads text from the lit points of a sparse drawing, such as the panels a robot painted.
+pub fn read_points<V>(points: &HashMap<Pt<i32>, V>, lit: impl Fn(&V) -> bool) -> Option<String> {
+    let lit_points = points
+        .iter()
+        .filter(|&(_, v)| lit(v))
+        .map(|(&p, _)| p)
+        .collect::<Vec<_>>();
+    let min_x = lit_points.iter().map(|p| p.x).min()?;
+    let min_y = lit_points.iter().map(|p| p.y).min()?;
+    let max_x = lit_points.iter().map(|p| p.x).max()?;
+    let max_y = lit_points.iter().map(|p| p.y).max()?;
+    let mut rows = vec![vec![false; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+    for p in lit_points {
+        rows[(p.y - min_y) as usize][(p.x - min_x) as usize] = true;
+    }
+    read(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap(s: &str) -> Vec<Vec<bool>> {
+        s.lines()
+            .map(|l| l.bytes().map(|b| b == b'#').collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_small() {
+        let image = bitmap(
+            "\
+.##..#...##..#.#..##.
+#..#.#...###..#..#..#
+#....#...#..#.#..#..#
+#....#...#..#.#..####
+#..#.#...#..#.#..#..#
+.##..####.##..#..#..#",
+        );
+        assert_eq!(read(&image), None);
+
+        let image = bitmap(
+            "\
+.......................
+..##..#...####..####...
+.#..#.#...##..#.#......
+.#.....#.#.#..#.###....
+.#......#..###..#......
+.#..#...#..#.#..#......
+..##....#..#..#.####...
+.......................",
+        );
+        assert_eq!(read(&image).as_deref(), Some("CYRE"));
+    }
+
+    #[test]
+    fn test_leading_gap() {
+        let image = bitmap(
+            "\
+.###.####
+..#..#...
+..#..###.
+..#..#...
+..#..#...
+.###.#...",
+        );
+        assert_eq!(read(&image).as_deref(), Some("IF"));
+    }
+
+    #[test]
+    fn test_large() {
+        let image = bitmap(
+            "\
+#....#..######
+##...#..#.....
+##...#..#.....
+#.#..#..#.....
+#.#..#..#####.
+#..#.#..#.....
+#..#.#..#.....
+#...##..#.....
+#...##..#.....
+#....#..######",
+        );
+        assert_eq!(read(&image).as_deref(), Some("NE"));
+    }
+
+    #[test]
+    fn test_points() {
+        let mut points = HashMap::new();
+        for (y, row) in bitmap("#..#\n#..#\n####\n#..#\n#..#\n#..#")
+            .into_iter()
+            .enumerate()
+        {
+            for (x, lit) in row.into_iter().enumerate() {
+                points.insert(
+                    Pt {
+                        x: x as i32 - 10,
+                        y: y as i32 + 3,
+                    },
+                    u8::from(lit),
+                );
+            }
+        }
+        assert_eq!(read_points(&points, |&v| v == 1).as_deref(), Some("H"));
+        assert_eq!(read_points(&points, |&v| v == 2), None);
+    }
+}